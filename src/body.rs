@@ -0,0 +1,110 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::f32::consts::PI;
+
+// Elliptical orbit around a body's translation, with the center of motion at
+// one focus. The ellipse lies in the xz plane tipped about x by
+// `inclination`; angles are in degrees and `speed` is radians per frame.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Orbit {
+  pub semi_major_axis: f32,
+  #[serde(default)]
+  pub eccentricity: f32,
+  #[serde(default)]
+  pub inclination: f32,
+  #[serde(default)]
+  pub phase: f32,
+  #[serde(default)]
+  pub speed: f32,
+}
+
+impl Orbit {
+  // Offset from the orbit's focus at `time`. The angle advances uniformly,
+  // which is not Keplerian but keeps the motion easy to predict.
+  pub fn offset(&self, time: u32) -> Vec3 {
+    let e = self.eccentricity.clamp(0.0, 0.99);
+    let a = self.semi_major_axis;
+    let b = a * (1.0 - e * e).sqrt();
+    let angle = self.phase.to_radians() + self.speed * time as f32;
+
+    let x = a * (angle.cos() - e);
+    let z = b * angle.sin();
+    let (sin_i, cos_i) = self.inclination.to_radians().sin_cos();
+    Vec3::new(x, z * sin_i, z * cos_i)
+  }
+}
+
+pub struct Body {
+  pub shader_index: usize,
+  pub translation: Vec3,
+  pub scale: f32,
+  pub axial_tilt: f32,
+  pub axial_spin_speed: f32,
+  pub material: usize,
+  pub orbit: Option<Orbit>,
+  pub emission: f32,
+}
+
+impl Body {
+  pub fn new(shader_index: usize, translation: Vec3, scale: f32) -> Self {
+    Body {
+      shader_index,
+      translation,
+      scale,
+      axial_tilt: 0.0,
+      axial_spin_speed: 0.0,
+      material: 0,
+      orbit: None,
+      emission: 0.0,
+    }
+  }
+
+  pub fn with_spin(mut self, axial_tilt: f32, axial_spin_speed: f32) -> Self {
+    self.axial_tilt = axial_tilt;
+    self.axial_spin_speed = axial_spin_speed;
+    self
+  }
+
+  pub fn with_orbit(mut self, orbit: Option<Orbit>) -> Self {
+    self.orbit = orbit;
+    self
+  }
+
+  pub fn with_emission(mut self, emission: f32) -> Self {
+    self.emission = emission.max(0.0);
+    self
+  }
+
+  // World-space center at `time`: the translation plus the orbital offset.
+  pub fn position(&self, time: u32) -> Vec3 {
+    match &self.orbit {
+      Some(orbit) => self.translation + orbit.offset(time),
+      None => self.translation,
+    }
+  }
+
+  // Spin is applied about the local y axis first, then the tilt about z,
+  // so the spin axis stays fixed while the surface turns under it.
+  pub fn rotation(&self, time: u32) -> Vec3 {
+    let spin = (time as f32 * self.axial_spin_speed) % (2.0 * PI);
+    Vec3::new(0.0, spin, self.axial_tilt)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn inclination_tips_the_orbit_out_of_the_xz_plane() {
+    let orbit = |inclination: f32| Orbit { semi_major_axis: 3.0, eccentricity: 0.2, inclination, phase: 90.0, speed: 0.0 };
+    let flat = Body::new(1, Vec3::zeros(), 1.0).with_orbit(Some(orbit(0.0)));
+    let tipped = Body::new(1, Vec3::zeros(), 1.0).with_orbit(Some(orbit(30.0)));
+
+    let (flat, tipped) = (flat.position(50), tipped.position(50));
+    assert!(flat.y.abs() < 1e-5);
+    assert!(tipped.y > 1.0, "{:?}", tipped);
+    // Tipping only rotates the orbit about x, so the distance is unchanged.
+    assert!((flat.magnitude() - tipped.magnitude()).abs() < 1e-5);
+  }
+}
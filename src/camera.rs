@@ -6,19 +6,36 @@ pub struct Camera {
   pub eye: Vec3,
   pub center: Vec3,
   pub up: Vec3,
-  pub has_changed: bool
+  pub fov: f32,
+  pub has_changed: bool,
+  initial: (Vec3, Vec3, Vec3),
 }
 
+pub const DEFAULT_FOV: f32 = 45.0 * PI / 180.0;
+const MIN_ZOOM_DISTANCE: f32 = 0.5;
+const MAX_ZOOM_DISTANCE: f32 = 200.0;
+
 impl Camera {
   pub fn new(eye: Vec3, center: Vec3, up: Vec3) -> Self {
     Camera {
       eye,
       center,
       up,
+      fov: DEFAULT_FOV,
       has_changed: true,
+      initial: (eye, center, up),
     }
   }
 
+  pub fn reset(&mut self) {
+    let (eye, center, up) = self.initial;
+    self.eye = eye;
+    self.center = center;
+    self.up = up;
+    self.fov = DEFAULT_FOV;
+    self.has_changed = true;
+  }
+
 
   pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
     let radius_vector = self.eye - self.center;
@@ -42,9 +59,26 @@ impl Camera {
     self.has_changed = true;
   }
 
+  // Turntable orbit around the vertical axis through `center`, advancing by
+  // `angular_speed * dt` radians. Radius and height are preserved, so the
+  // eye returns to its start after a full turn.
+  pub fn auto_orbit(&mut self, angular_speed: f32, dt: f32) {
+    let offset = rotate_vec3(&(self.eye - self.center), angular_speed * dt, &Vec3::new(0.0, 1.0, 0.0));
+    self.eye = self.center + offset;
+    self.has_changed = true;
+  }
+
+  // Scales the eye-to-center distance by `1 - delta`, so equal steps feel
+  // the same near and far. The distance is clamped so the eye can never
+  // reach the center or fly off past the far plane.
   pub fn zoom(&mut self, delta: f32) {
-    let direction = (self.center - self.eye).normalize();
-    self.eye += direction * delta;
+    let offset = self.eye - self.center;
+    let distance = offset.magnitude();
+    if distance <= f32::EPSILON {
+      return;
+    }
+    let new_distance = (distance * (1.0 - delta)).clamp(MIN_ZOOM_DISTANCE, MAX_ZOOM_DISTANCE);
+    self.eye = self.center + offset * (new_distance / distance);
     self.has_changed = true;
   }
 
@@ -64,5 +98,98 @@ impl Camera {
     self.has_changed = true;
   }
 
+  // Slides eye and center together across the view plane, keeping the view
+  // direction. Deltas are fractions of the screen height along screen x
+  // (right) and y (down), scaled so a point at the center's distance moves
+  // by exactly that fraction of the view.
+  pub fn pan(&mut self, screen_dx: f32, screen_dy: f32) {
+    let forward = self.center - self.eye;
+    let distance = forward.magnitude();
+    let Some(right) = forward.cross(&self.up).try_normalize(f32::EPSILON) else {
+      return;
+    };
+    let up = right.cross(&forward).normalize();
+    let view_height = 2.0 * distance * (self.fov / 2.0).tan();
+    let offset = (right * screen_dx - up * screen_dy) * view_height;
+    self.eye += offset;
+    self.center += offset;
+    self.has_changed = true;
+  }
+
+  pub fn focus(&mut self, target: Vec3) {
+    let offset = self.eye - self.center;
+    self.center = target;
+    self.eye = target + offset;
+    self.has_changed = true;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn pan_right_moves_eye_and_center_along_the_right_vector() {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let offset_before = camera.eye - camera.center;
+    camera.pan(0.1, 0.0);
+
+    let expected = 0.1 * 2.0 * 5.0 * (DEFAULT_FOV / 2.0).tan();
+    assert!((camera.center - Vec3::new(expected, 0.0, 0.0)).magnitude() < 1e-6);
+    assert!((camera.eye - Vec3::new(expected, 0.0, 5.0)).magnitude() < 1e-6);
+    assert!((camera.eye - camera.center - offset_before).magnitude() < 1e-6);
+  }
+
+  #[test]
+  fn reset_restores_the_exact_starting_view() {
+    let (eye, center, up) = (Vec3::new(1.0, 2.0, 6.0), Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let mut camera = Camera::new(eye, center, up);
+    camera.orbit(0.7, 0.3);
+    camera.zoom(0.4);
+    camera.pan(0.2, -0.1);
+    camera.fov = 1.0;
+    assert_ne!(camera.eye, eye);
+    assert_ne!(camera.center, center);
+
+    camera.has_changed = false;
+    camera.reset();
+    assert_eq!((camera.eye, camera.center, camera.up), (eye, center, up));
+    assert_eq!(camera.fov, DEFAULT_FOV);
+    assert!(camera.has_changed);
+  }
+
+  #[test]
+  fn full_turn_of_auto_orbit_returns_the_eye_at_a_constant_radius() {
+    let start = Vec3::new(3.0, 1.5, 4.0);
+    let mut camera = Camera::new(start, Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let radius = start.magnitude();
+    let steps = 360;
+    for _ in 0..steps {
+      camera.auto_orbit(2.0 * PI / steps as f32, 1.0);
+      assert!((camera.eye.magnitude() - radius).abs() < 1e-3);
+      assert!((camera.eye.y - start.y).abs() < 1e-3);
+    }
+    assert!((camera.eye - start).magnitude() < 1e-3, "{:?}", camera.eye);
+  }
 
+  #[test]
+  fn equal_zoom_steps_shrink_the_distance_by_a_constant_ratio() {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 100.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let mut distances = vec![100.0];
+    for _ in 0..10 {
+      camera.zoom(0.2);
+      distances.push((camera.eye - camera.center).magnitude());
+    }
+    for pair in distances.windows(2).filter(|pair| pair[1] > MIN_ZOOM_DISTANCE) {
+      assert!((pair[1] / pair[0] - 0.8).abs() < 1e-4, "{:?}", pair);
+    }
+
+    for _ in 0..200 {
+      camera.zoom(0.5);
+    }
+    let distance = (camera.eye - camera.center).magnitude();
+    assert!(distance > 0.0 && (distance - MIN_ZOOM_DISTANCE).abs() < 1e-5, "{}", distance);
+    camera.zoom(1.0);
+    assert!((camera.eye - camera.center).magnitude() > 0.0);
+  }
 }
@@ -0,0 +1,98 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::vertex::Vertex;
+
+// Signed distance to each of the six clip-space planes -w <= x, y, z <= w;
+// a vertex is inside a plane when its distance is >= 0.
+const PLANES: [fn(&Vec4) -> f32; 6] = [
+    |p| p.w + p.x,
+    |p| p.w - p.x,
+    |p| p.w + p.y,
+    |p| p.w - p.y,
+    |p| p.w + p.z,
+    |p| p.w - p.z,
+];
+
+// Sutherland-Hodgman clipping of one triangle against the full view volume.
+// The surviving polygon is fanned back into triangles, and every vertex gets
+// its screen position recomputed from the clipped clip-space position, so no
+// out-of-range coordinates reach the rasterizer.
+pub fn clip_triangle(triangle: &[Vertex; 3], viewport_matrix: &Mat4) -> Vec<[Vertex; 3]> {
+    let fully_inside = triangle.iter()
+        .all(|v| PLANES.iter().all(|plane| plane(&v.clip_position) >= 0.0));
+    if fully_inside {
+        return vec![triangle.clone()];
+    }
+
+    let mut polygon: Vec<Vertex> = triangle.to_vec();
+    for plane in PLANES.iter() {
+        if polygon.is_empty() {
+            break;
+        }
+
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let current = &polygon[i];
+            let next = &polygon[(i + 1) % polygon.len()];
+            let d_current = plane(&current.clip_position);
+            let d_next = plane(&next.clip_position);
+
+            if d_current >= 0.0 {
+                clipped.push(current.clone());
+            }
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                let t = d_current / (d_current - d_next);
+                clipped.push(current.lerp(next, t));
+            }
+        }
+        polygon = clipped;
+    }
+
+    if polygon.len() < 3 {
+        return Vec::new();
+    }
+
+    for vertex in polygon.iter_mut() {
+        vertex.transformed_position = to_screen(&vertex.clip_position, viewport_matrix);
+    }
+
+    (1..polygon.len() - 1)
+        .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+        .collect()
+}
+
+fn to_screen(clip: &Vec4, viewport_matrix: &Mat4) -> Vec3 {
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = viewport_matrix * ndc;
+    Vec3::new(screen.x, screen.y, screen.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    #[test]
+    fn clipping_at_two_planes_keeps_vertices_inside_and_attributes_on_the_surface() {
+        // With w = 1 throughout, texture coordinates equal to clip x and y
+        // are a linear attribute, so the new vertices must carry exactly
+        // their own position.
+        let vertex = |x: f32, y: f32| {
+            let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(x, y));
+            vertex.ao = 0.5 + 0.1 * x;
+            vertex
+        };
+        let triangle = [vertex(-3.0, 0.0), vertex(0.5, -0.5), vertex(0.0, 3.0)];
+        let clipped = clip_triangle(&triangle, &Mat4::identity());
+        assert!(!clipped.is_empty());
+
+        let vertices: Vec<&Vertex> = clipped.iter().flatten().collect();
+        for vertex in &vertices {
+            let p = vertex.clip_position;
+            assert!(p.x.abs() <= p.w + 1e-6 && p.y.abs() <= p.w + 1e-6, "outside the view volume: {:?}", p);
+            assert!((vertex.tex_coords - p.xy()).magnitude() < 1e-5, "tex_coords {:?} at {:?}", vertex.tex_coords, p);
+            assert!((vertex.ao - (0.5 + 0.1 * p.x)).abs() < 1e-5);
+        }
+        assert!(vertices.iter().any(|vertex| (vertex.clip_position.x + 1.0).abs() < 1e-6), "nothing on the left plane");
+        assert!(vertices.iter().any(|vertex| (vertex.clip_position.y - 1.0).abs() < 1e-6), "nothing on the top plane");
+    }
+}
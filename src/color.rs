@@ -18,10 +18,27 @@ impl Color {
         Color { r: 0, g: 0, b: 0 }
     }
 
-    pub fn to_hex(&self) -> u32 {
+    pub const fn from_hex(hex: u32) -> Self {
+        Color {
+            r: ((hex >> 16) & 0xFF) as u8,
+            g: ((hex >> 8) & 0xFF) as u8,
+            b: (hex & 0xFF) as u8,
+        }
+    }
+
+    pub fn is_black(self) -> bool {
+        self.r == 0 && self.g == 0 && self.b == 0
+    }
+
+    pub fn to_hex(self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
+    // Rec. 709 relative luminance in [0, 1].
+    pub fn luminance(self) -> f32 {
+        (0.2126 * self.r as f32 + 0.7152 * self.g as f32 + 0.0722 * self.b as f32) / 255.0
+    }
+
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
         Color {
@@ -52,11 +69,13 @@ use std::ops::Mul;
 impl Mul<f32> for Color {
     type Output = Color;
 
+    // Float-to-int `as` casts saturate and turn NaN into 0, so overbright
+    // and bad shader values clamp instead of wrapping.
     fn mul(self, scalar: f32) -> Color {
         Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            r: (self.r as f32 * scalar) as u8,
+            g: (self.g as f32 * scalar) as u8,
+            b: (self.b as f32 * scalar) as u8,
         }
     }
 }
@@ -67,3 +86,23 @@ impl fmt::Display for Color {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_saturates_instead_of_wrapping() {
+        let sum = Color::new(200, 0, 0) + Color::new(100, 0, 0);
+        assert_eq!((sum.r, sum.g, sum.b), (255, 0, 0));
+    }
+
+    #[test]
+    fn mul_saturates_and_maps_nan_to_black() {
+        let doubled = Color::new(200, 0, 0) * 2.0;
+        assert_eq!((doubled.r, doubled.g, doubled.b), (255, 0, 0));
+        let negative = Color::new(200, 100, 50) * -1.0;
+        assert_eq!((negative.r, negative.g, negative.b), (0, 0, 0));
+        let nan = Color::new(200, 100, 50) * f32::NAN;
+        assert_eq!((nan.r, nan.g, nan.b), (0, 0, 0));
+    }
+}
@@ -4,22 +4,23 @@ use crate::color::Color;
 
 pub struct Fragment {
     pub position: Vec2,
-    pub color: Color,
     pub depth: f32,
     pub normal: Vec3,
     pub intensity: f32,
     pub vertex_position: Vec3,
+    // Object-space position for procedural noise lookups only. It starts
+    // equal to `vertex_position`; material presets rescale it to stretch a
+    // pattern without moving geometry-derived features like rings.
+    pub noise_position: Vec3,
+    pub world_position: Vec3,
+    pub ao: f32,
+    pub roughness: f32,
+    pub vertex_color: Color,
+    pub tex_coords: Vec2,
+    pub velocity: Vec2,
+    pub shaded_color: Color,
+    pub shaded_emission: Color,
+    pub shaded_opacity: f32,
+    // Taken from the triangle's first vertex; materials don't blend.
+    pub material_id: Option<usize>,
 }
-
-impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32, normal: Vec3, intensity: f32, vertex_position: Vec3,) -> Self {
-        Fragment {
-            position: Vec2::new(x, y),
-            color,
-            depth,
-            normal,
-            intensity,
-            vertex_position
-        }
-    }
-}
\ No newline at end of file
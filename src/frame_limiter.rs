@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+
+pub struct FrameLimiter {
+    target: Duration,
+    frame_start: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(target: Duration) -> Self {
+        FrameLimiter {
+            target,
+            frame_start: Instant::now(),
+        }
+    }
+
+    pub fn target(&self) -> Duration {
+        self.target
+    }
+
+    pub fn begin(&mut self) {
+        self.frame_start = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.frame_start.elapsed()
+    }
+
+    // How long to sleep after a frame that took `elapsed`; zero when the frame
+    // already blew its budget.
+    pub fn remaining(&self, elapsed: Duration) -> Duration {
+        self.target.saturating_sub(elapsed)
+    }
+
+    pub fn end_and_wait(&self) {
+        let remaining = self.remaining(self.elapsed());
+        if !remaining.is_zero() {
+            std::thread::sleep(remaining);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaining_is_the_unused_part_of_the_budget() {
+        let limiter = FrameLimiter::new(Duration::from_millis(16));
+        assert_eq!(limiter.remaining(Duration::from_millis(5)), Duration::from_millis(11));
+        assert_eq!(limiter.remaining(Duration::from_millis(20)), Duration::ZERO);
+    }
+}
@@ -1,11 +1,122 @@
+use nalgebra_glm::{Vec2, Vec3};
+use crate::texture::Texture;
+use crate::hdr::save_hdr;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Replace,
+    Add,
+    Alpha,
+}
+
+impl BlendMode {
+    pub fn next(self) -> Self {
+        match self {
+            BlendMode::Replace => BlendMode::Add,
+            BlendMode::Add => BlendMode::Alpha,
+            BlendMode::Alpha => BlendMode::Replace,
+        }
+    }
+}
+
+// How geometry's depth reaches the depth test. AlwaysFar pins every fragment
+// to the far plane, for skyboxes that must sit behind all real geometry no
+// matter how large they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DepthMode {
+    #[default]
+    Normal,
+    AlwaysFar,
+}
+
+// Falloff of analytic edge coverage across the one-pixel rim of
+// antialiased shapes, from 0 at the outer edge to 1 fully inside. Smoothstep
+// eases both ends, which reads as a softer, rounder edge than Linear's ramp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoverageCurve {
+    #[default]
+    Linear,
+    Smoothstep,
+}
+
+impl CoverageCurve {
+    pub fn next(self) -> Self {
+        match self {
+            CoverageCurve::Linear => CoverageCurve::Smoothstep,
+            CoverageCurve::Smoothstep => CoverageCurve::Linear,
+        }
+    }
+
+    // Coverage for a pixel `distance` pixels inside the edge, clamped to
+    // [0, 1].
+    pub fn coverage(self, distance: f32) -> f32 {
+        let t = distance.clamp(0.0, 1.0);
+        match self {
+            CoverageCurve::Linear => t,
+            CoverageCurve::Smoothstep => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+// Channel order of the presented buffer. Everything is rendered as
+// 0x00RRGGBB; Bgr swaps red and blue on the way to a display that expects
+// the other order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorOrder {
+    #[default]
+    Rgb,
+    Bgr,
+}
+
+impl ColorOrder {
+    pub fn next(self) -> Self {
+        match self {
+            ColorOrder::Rgb => ColorOrder::Bgr,
+            ColorOrder::Bgr => ColorOrder::Rgb,
+        }
+    }
+
+    pub fn present(self, buffer: &[u32]) -> Cow<'_, [u32]> {
+        match self {
+            ColorOrder::Rgb => Cow::Borrowed(buffer),
+            ColorOrder::Bgr => Cow::Owned(
+                buffer
+                    .iter()
+                    .map(|&pixel| ((pixel & 0xFF) << 16) | (pixel & 0x00FF00) | ((pixel >> 16) & 0xFF))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+const HDR_EMISSION_SCALE: f32 = 4.0;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,         
     pub zbuffer: Vec<f32>,        
     pub emission_buffer: Vec<u32>, 
+    pub id_buffer: Vec<u32>,
+    pub velocity_buffer: Vec<Vec2>,
+    // Per-pixel coverage from 0 (background only) to 255 (opaque geometry),
+    // exported as alpha by `save_png_rgba`.
+    pub alpha_buffer: Vec<u8>,
+    // Running average of past frames for temporal motion blur, kept across
+    // `clear` so it builds up over several frames.
+    accum_buffer: Vec<Vec3>,
+    accum_frames: u32,
     background_color: u32,
+    background_gradient: Option<(u32, u32)>,
     current_color: u32,
+    scissor: Option<(usize, usize, usize, usize)>,
+    current_id: u32,
+    current_velocity: Vec2,
+    coverage_curve: CoverageCurve,
 }
 
 impl Framebuffer {
@@ -16,14 +127,55 @@ impl Framebuffer {
             buffer: vec![0; width * height],                 
             zbuffer: vec![f32::INFINITY; width * height],     
             emission_buffer: vec![0; width * height],         
+            id_buffer: vec![0; width * height],
+            velocity_buffer: vec![Vec2::zeros(); width * height],
+            alpha_buffer: vec![0; width * height],
+            accum_buffer: vec![Vec3::zeros(); width * height],
+            accum_frames: 0,
             background_color: 0x000000,                       
+            background_gradient: None,
             current_color: 0xFFFFFF,                          
+            scissor: None,
+            current_id: 0,
+            current_velocity: Vec2::zeros(),
+            coverage_curve: CoverageCurve::default(),
         }
     }
 
+    // A cleared framebuffer of the same size and background that carries on
+    // the drawing state (id tag, velocity, scissor), so passes rendered into
+    // it are tagged and clipped as they would be in the original.
+    pub fn blank_copy(&self) -> Framebuffer {
+        let mut copy = Framebuffer::new(self.width, self.height);
+        copy.background_color = self.background_color;
+        copy.background_gradient = self.background_gradient;
+        copy.coverage_curve = self.coverage_curve;
+        copy.clear();
+        copy.current_color = self.current_color;
+        copy.current_id = self.current_id;
+        copy.current_velocity = self.current_velocity;
+        copy.scissor = self.scissor;
+        copy
+    }
+
+    // Resets every pixel of every buffer. The scissor rectangle only limits
+    // drawing, never clearing, so a frame always starts from a clean slate.
     pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
+        match self.background_gradient {
+            Some((top, bottom)) => {
+                for (y, row) in self.buffer.chunks_mut(self.width).enumerate() {
+                    let t = y as f32 / (self.height.max(2) - 1) as f32;
+                    let color = lerp_hex(top, bottom, t);
+                    for pixel in row.iter_mut() {
+                        *pixel = color;
+                    }
+                }
+            }
+            None => {
+                for pixel in self.buffer.iter_mut() {
+                    *pixel = self.background_color;
+                }
+            }
         }
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
@@ -31,25 +183,426 @@ impl Framebuffer {
         for emission in self.emission_buffer.iter_mut() {
             *emission = 0;
         }
+        for id in self.id_buffer.iter_mut() {
+            *id = 0;
+        }
+        for velocity in self.velocity_buffer.iter_mut() {
+            *velocity = Vec2::zeros();
+        }
+        for alpha in self.alpha_buffer.iter_mut() {
+            *alpha = 0;
+        }
+        self.current_id = 0;
+    }
+
+    // Debug-build check that no stale depth survived the last clear.
+    pub fn debug_assert_all_infinity(&self) {
+        debug_assert!(self.zbuffer.iter().all(|&depth| depth == f32::INFINITY), "zbuffer not cleared");
+    }
+
+    // Nearest-neighbour upscale of the color buffer to `width` x `height`, for
+    // presenting a reduced internal resolution at the window's size.
+    pub fn upscaled(&self, width: usize, height: usize) -> Cow<'_, [u32]> {
+        if width == self.width && height == self.height {
+            return Cow::Borrowed(&self.buffer);
+        }
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = (y * self.height / height) * self.width;
+            pixels.extend((0..width).map(|x| self.buffer[row + x * self.width / width]));
+        }
+        Cow::Owned(pixels)
+    }
+
+    pub fn as_texture(&self) -> Texture {
+        Texture::new(self.width, self.height, self.buffer.clone())
+    }
+
+    // Color with coverage in the top byte, as 0xAARRGGBB.
+    pub fn to_argb(&self) -> Vec<u32> {
+        self.buffer
+            .iter()
+            .zip(&self.alpha_buffer)
+            .map(|(&color, &alpha)| (u32::from(alpha) << 24) | (color & 0xFFFFFF))
+            .collect()
+    }
+
+    // Linear float color with emission added on top at HDR_EMISSION_SCALE, so
+    // emitters keep their brightness above 1.0 instead of clipping as they do
+    // in the 8-bit buffer. This is an approximation: nothing is rendered in
+    // float, so both inputs are already quantized and clamped to 8 bits, and
+    // HDR_EMISSION_SCALE is a chosen brightness for full emission rather than
+    // anything the shaders computed.
+    pub fn to_hdr(&self) -> Vec<[f32; 3]> {
+        let channels = |pixel: u32| [
+            ((pixel >> 16) & 0xFF) as f32 / 255.0,
+            ((pixel >> 8) & 0xFF) as f32 / 255.0,
+            (pixel & 0xFF) as f32 / 255.0,
+        ];
+        self.buffer
+            .iter()
+            .zip(&self.emission_buffer)
+            .map(|(&color, &emission)| {
+                let (c, e) = (channels(color), channels(emission));
+                [0, 1, 2].map(|i| c[i] + e[i] * HDR_EMISSION_SCALE)
+            })
+            .collect()
+    }
+
+    pub fn save_hdr(&self, path: &str) -> Result<(), String> {
+        save_hdr(path, self.width, self.height, &self.to_hdr())
+    }
+
+    // Writes a 32-bit RGBA PNG where pixels no geometry touched are fully
+    // transparent.
+    pub fn save_png_rgba(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|err| format!("failed to create {}: {}", path, err))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let data: Vec<u8> = self
+            .to_argb()
+            .iter()
+            .flat_map(|&pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8, (pixel >> 24) as u8])
+            .collect();
+
+        // Finishing explicitly surfaces errors that dropping the writer
+        // would swallow, so a reported success is always a complete file.
+        encoder
+            .write_header()
+            .and_then(|mut writer| {
+                writer.write_image_data(&data)?;
+                writer.finish()
+            })
+            .map_err(|err| format!("failed to write {}: {}", path, err))
     }
 
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
+        self.background_gradient = None;
+    }
+
+    pub fn set_background_gradient(&mut self, top: u32, bottom: u32) {
+        self.background_color = top;
+        self.background_gradient = Some((top, bottom));
     }
 
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
 
-    pub fn point_with_emission(&mut self, x: usize, y: usize, depth: f32, emission: u32) {
-        if x < self.width && y < self.height {
+    // Blends the current frame into the running average, keeping
+    // `persistence` of the history, and writes the result back as the frame.
+    pub fn accumulate(&mut self, persistence: f32) {
+        let keep = if self.accum_frames == 0 { 0.0 } else { persistence };
+        for (pixel, accum) in self.buffer.iter_mut().zip(self.accum_buffer.iter_mut()) {
+            let current = Vec3::new(
+                ((*pixel >> 16) & 0xFF) as f32,
+                ((*pixel >> 8) & 0xFF) as f32,
+                (*pixel & 0xFF) as f32,
+            );
+            *accum = *accum * keep + current * (1.0 - keep);
+            *pixel = ((accum.x.round() as u32) << 16) | ((accum.y.round() as u32) << 8) | accum.z.round() as u32;
+        }
+        self.accum_frames = self.accum_frames.saturating_add(1);
+    }
+
+    // Drops the accumulated history so the next frame starts fresh.
+    pub fn clear_accum(&mut self) {
+        self.accum_frames = 0;
+    }
+
+    // Screen-space motion, in pixels per frame, stored with subsequent opaque writes.
+    pub fn set_current_velocity(&mut self, velocity: Vec2) {
+        self.current_velocity = velocity;
+    }
+
+    pub fn set_coverage_curve(&mut self, curve: CoverageCurve) {
+        self.coverage_curve = curve;
+    }
+
+    // Tags subsequent opaque writes with a body index for picking.
+    pub fn set_current_id(&mut self, body_index: Option<usize>) {
+        self.current_id = body_index.map_or(0, |index| index as u32 + 1);
+    }
+
+    pub fn current_id(&self) -> Option<usize> {
+        self.current_id.checked_sub(1).map(|id| id as usize)
+    }
+
+    pub fn read_id(&self, x: usize, y: usize) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        match self.id_buffer[y * self.width + x] {
+            0 => None,
+            id => Some(id as usize - 1),
+        }
+    }
+
+    // Restricts writes to the half-open rectangle [x0, x1) x [y0, y1).
+    pub fn set_scissor(&mut self, scissor: Option<(usize, usize, usize, usize)>) {
+        self.scissor = scissor;
+    }
+
+    fn in_scissor(&self, x: usize, y: usize) -> bool {
+        match self.scissor {
+            Some((x0, y0, x1, y1)) => x >= x0 && x < x1 && y >= y0 && y < y1,
+            None => true,
+        }
+    }
+
+    // Returns whether the write passed the depth test.
+    pub fn point_with_emission(&mut self, x: usize, y: usize, depth: f32, emission: u32) -> bool {
+        if x < self.width && y < self.height && self.in_scissor(x, y) {
             let index = y * self.width + x;
 
             if self.zbuffer[index] > depth {
                 self.buffer[index] = self.current_color;  
                 self.emission_buffer[index] = emission;   
                 self.zbuffer[index] = depth;              
+                self.id_buffer[index] = self.current_id;
+                self.velocity_buffer[index] = self.current_velocity;
+                self.alpha_buffer[index] = 255;
+                return true;
+            }
+        }
+        false
+    }
+
+    // Stores a multisample-resolved pixel. `coverage` is the fraction of its
+    // samples that geometry reached; depth only ever moves closer.
+    pub fn write_resolved(&mut self, index: usize, color: u32, emission: u32, depth: f32, velocity: Vec2, coverage: f32) {
+        if !self.in_scissor(index % self.width, index / self.width) {
+            return;
+        }
+        self.buffer[index] = color;
+        self.emission_buffer[index] = emission;
+        if depth < self.zbuffer[index] {
+            self.zbuffer[index] = depth;
+            self.id_buffer[index] = self.current_id;
+            self.velocity_buffer[index] = velocity;
+        }
+        self.alpha_buffer[index] = over_alpha(self.alpha_buffer[index], coverage);
+    }
+
+    // Adds glow into the emission buffer behind a depth test without touching
+    // color or depth, for translucent emitters like the solar corona.
+    pub fn add_emission(&mut self, x: usize, y: usize, depth: f32, emission: u32) {
+        if x < self.width && y < self.height && self.in_scissor(x, y) {
+            let index = y * self.width + x;
+            if self.zbuffer[index] > depth {
+                self.emission_buffer[index] = add_hex(self.emission_buffer[index], emission);
+            }
+        }
+    }
+
+    // Filled disk centered on (cx, cy) with a one-pixel antialiased rim shaped
+    // by the coverage curve. Fully covered pixels are written like opaque
+    // geometry; rim pixels are blended by their coverage without touching
+    // depth.
+    pub fn draw_sprite(&mut self, cx: f32, cy: f32, radius: f32, color: u32, depth: f32) {
+        let reach = radius + 0.5;
+        let min_x = (cx - reach).floor().max(0.0) as usize;
+        let min_y = (cy - reach).floor().max(0.0) as usize;
+        let max_x = ((cx + reach).ceil() as i64).min(self.width as i64 - 1);
+        let max_y = ((cy + reach).ceil() as i64).min(self.height as i64 - 1);
+        if max_x < 0 || max_y < 0 {
+            return;
+        }
+
+        for y in min_y..=max_y as usize {
+            for x in min_x..=max_x as usize {
+                let dx = x as f32 + 0.5 - cx;
+                let dy = y as f32 + 0.5 - cy;
+                let coverage = self.coverage_curve.coverage(reach - (dx * dx + dy * dy).sqrt());
+                if coverage >= 1.0 {
+                    self.blend_point(BlendMode::Replace, x, y, depth, color, 1.0);
+                } else if coverage > 0.0 {
+                    self.blend_point(BlendMode::Alpha, x, y, depth, color, coverage);
+                }
+            }
+        }
+    }
+
+    // Depth-tested write that combines `color` with what's already there.
+    // Add and Alpha are meant for translucent effects, so they leave the
+    // zbuffer untouched and anything behind them still shows through. A depth
+    // of infinity passes only over cleared background, for things at
+    // infinity like stars.
+    pub fn blend_point(&mut self, mode: BlendMode, x: usize, y: usize, depth: f32, color: u32, alpha: f32) {
+        if x >= self.width || y >= self.height || !self.in_scissor(x, y) {
+            return;
+        }
+
+        let index = y * self.width + x;
+        let at_infinity = depth == f32::INFINITY && self.zbuffer[index] == f32::INFINITY;
+        if self.zbuffer[index] <= depth && !at_infinity {
+            return;
+        }
+
+        let alpha = alpha.clamp(0.0, 1.0);
+        match mode {
+            BlendMode::Replace => {
+                self.buffer[index] = color;
+                self.zbuffer[index] = depth;
+                self.id_buffer[index] = self.current_id;
+                self.velocity_buffer[index] = self.current_velocity;
+                self.alpha_buffer[index] = 255;
+            }
+            BlendMode::Add => {
+                self.buffer[index] = add_hex(self.buffer[index], color);
+                self.alpha_buffer[index] = over_alpha(self.alpha_buffer[index], alpha);
+            }
+            BlendMode::Alpha => {
+                self.buffer[index] = lerp_hex(self.buffer[index], color, alpha);
+                self.alpha_buffer[index] = over_alpha(self.alpha_buffer[index], alpha);
+            }
+        }
+    }
+}
+
+// Coverage after compositing a layer of `alpha` over existing coverage.
+fn over_alpha(existing: u8, alpha: f32) -> u8 {
+    let existing = existing as f32 / 255.0;
+    ((existing + alpha * (1.0 - existing)) * 255.0).round() as u8
+}
+
+fn add_hex(a: u32, b: u32) -> u32 {
+    let channel = |shift: u32| (((a >> shift) & 0xFF) + ((b >> shift) & 0xFF)).min(0xFF) << shift;
+    channel(16) | channel(8) | channel(0)
+}
+
+fn lerp_hex(a: u32, b: u32, t: f32) -> u32 {
+    let channel = |shift: u32| {
+        let ca = ((a >> shift) & 0xFF) as f32;
+        let cb = ((b >> shift) & 0xFF) as f32;
+        ((ca + (cb - ca) * t).round() as u32) << shift
+    };
+
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{Filter, Wrap};
+
+    #[test]
+    fn coverage_curves_agree_at_the_extremes_and_mid_edge_but_differ_at_a_quarter() {
+        for curve in [CoverageCurve::Linear, CoverageCurve::Smoothstep] {
+            assert_eq!(curve.coverage(0.0), 0.0);
+            assert_eq!(curve.coverage(1.0), 1.0);
+            assert_eq!(curve.coverage(-2.0), 0.0);
+            assert_eq!(curve.coverage(3.0), 1.0);
+        }
+        // Smoothstep is symmetric about a pixel centered on the edge, where
+        // both give 0.5, so compare a quarter pixel inside instead.
+        assert_eq!(CoverageCurve::Smoothstep.coverage(0.5), 0.5);
+        assert_ne!(CoverageCurve::Linear.coverage(0.25), CoverageCurve::Smoothstep.coverage(0.25));
+    }
+
+    #[test]
+    fn add_saturates_overlapping_grays_and_replace_keeps_the_last_write() {
+        let mut framebuffer = Framebuffer::new(1, 1);
+        framebuffer.blend_point(BlendMode::Add, 0, 0, 0.5, 0x808080, 0.5);
+        assert_eq!(framebuffer.buffer[0], 0x808080);
+        framebuffer.blend_point(BlendMode::Add, 0, 0, 0.5, 0x808080, 0.5);
+        assert_eq!(framebuffer.buffer[0], 0xFFFFFF);
+        // Add leaves depth alone so later layers still pass.
+        assert_eq!(framebuffer.zbuffer[0], f32::INFINITY);
+
+        let mut framebuffer = Framebuffer::new(1, 1);
+        framebuffer.blend_point(BlendMode::Replace, 0, 0, 0.5, 0x808080, 1.0);
+        framebuffer.blend_point(BlendMode::Replace, 0, 0, 0.4, 0x202020, 1.0);
+        assert_eq!(framebuffer.buffer[0], 0x202020);
+        assert_eq!(framebuffer.zbuffer[0], 0.4);
+    }
+
+    #[test]
+    fn clear_resets_depth_outside_the_scissor_too() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                framebuffer.point_with_emission(x, y, 0.5, 0);
             }
         }
+        framebuffer.set_scissor(Some((0, 0, 2, 2)));
+        framebuffer.clear();
+        assert!(framebuffer.zbuffer.iter().all(|&depth| depth == f32::INFINITY));
+    }
+
+    #[test]
+    fn solid_color_rendered_to_a_texture_samples_back_unchanged() {
+        let mut framebuffer = Framebuffer::new(8, 6);
+        framebuffer.set_current_color(0x3C78B4);
+        for y in 0..framebuffer.height {
+            for x in 0..framebuffer.width {
+                framebuffer.point_with_emission(x, y, 0.5, 0);
+            }
+        }
+        let texture = framebuffer.as_texture();
+        assert_eq!((texture.width, texture.height), (8, 6));
+        for (u, v) in [(0.0, 0.0), (0.5, 0.5), (0.9, 0.2), (0.3, 0.99)] {
+            for filter in [Filter::Nearest, Filter::Bilinear] {
+                assert_eq!(texture.sample(u, v, filter, Wrap::Clamp, 0.0).to_hex(), 0x3C78B4);
+            }
+        }
+    }
+
+    #[test]
+    fn bgr_presents_red_and_blue_swapped() {
+        let buffer = [0xFF0000, 0x00FF00, 0x123456];
+        assert_eq!(&*ColorOrder::Rgb.present(&buffer), &buffer);
+        assert_eq!(&*ColorOrder::Bgr.present(&buffer), &[0x0000FF, 0x00FF00, 0x563412]);
+    }
+
+    #[test]
+    fn alpha_is_zero_over_background_and_full_over_geometry() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.set_background_color(0x202020);
+        framebuffer.clear();
+        framebuffer.set_current_color(0xFF8000);
+        framebuffer.point_with_emission(1, 2, 0.5, 0);
+
+        for (index, &alpha) in framebuffer.alpha_buffer.iter().enumerate() {
+            assert_eq!(alpha, if index == 2 * 4 + 1 { 255 } else { 0 });
+        }
+        let argb = framebuffer.to_argb();
+        assert_eq!(argb[2 * 4 + 1], 0xFFFF8000);
+        assert_eq!(argb[0], 0x00202020);
+    }
+
+    #[test]
+    fn point_with_emission_splits_surface_and_glow_into_their_buffers() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.set_current_color(0xFF0000);
+        assert!(framebuffer.point_with_emission(2, 1, 0.5, 0x0000FF));
+        assert_eq!(framebuffer.buffer[4 + 2], 0xFF0000);
+        assert_eq!(framebuffer.emission_buffer[4 + 2], 0x0000FF);
+        assert_eq!(framebuffer.zbuffer[4 + 2], 0.5);
+
+        // A farther write fails the depth test and leaves both buffers alone.
+        framebuffer.set_current_color(0x00FF00);
+        assert!(!framebuffer.point_with_emission(2, 1, 0.8, 0xFFFFFF));
+        assert_eq!(framebuffer.buffer[4 + 2], 0xFF0000);
+        assert_eq!(framebuffer.emission_buffer[4 + 2], 0x0000FF);
+    }
+
+    #[test]
+    fn sprite_is_opaque_at_the_center_and_fades_at_the_rim() {
+        let mut framebuffer = Framebuffer::new(17, 17);
+        framebuffer.draw_sprite(8.5, 8.5, 3.0, 0xFFFFFF, 0.5);
+        let alpha = |x: usize, y: usize| framebuffer.alpha_buffer[y * 17 + x];
+
+        assert_eq!(alpha(8, 8), 255);
+        assert_eq!(framebuffer.buffer[8 * 17 + 8], 0xFFFFFF);
+        assert_eq!(alpha(10, 8), 255);
+        // Rim pixels 2.83 and 3 pixels out are partly covered, the farther less.
+        assert!(alpha(10, 10) > alpha(11, 8));
+        assert!(alpha(11, 8) > 0 && alpha(10, 10) < 255);
+        assert_eq!(alpha(12, 8), 0);
+        assert_eq!(framebuffer.zbuffer[8 * 17 + 11], f32::INFINITY);
     }
 }
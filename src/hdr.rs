@@ -0,0 +1,65 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+// Writes linear RGB values to a Radiance .hdr file using uncompressed RGBE
+// scanlines. Values above 1.0 are kept; each pixel shares one exponent, so
+// precision is about 1% of its brightest channel.
+pub fn save_hdr(path: &str, width: usize, height: usize, pixels: &[[f32; 3]]) -> Result<(), String> {
+    if pixels.len() != width * height {
+        return Err(format!("cannot write {}: expected {} pixels, got {}", path, width * height, pixels.len()));
+    }
+
+    let file = File::create(path).map_err(|err| format!("failed to create {}: {}", path, err))?;
+    let mut writer = BufWriter::new(file);
+    let mut data = Vec::with_capacity(pixels.len() * 4);
+    for pixel in pixels {
+        data.extend_from_slice(&to_rgbe(*pixel));
+    }
+
+    write!(writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)
+        .and_then(|_| writer.write_all(&data))
+        .and_then(|_| writer.flush())
+        .map_err(|err| format!("failed to write {}: {}", path, err))
+}
+
+fn to_rgbe([r, g, b]: [f32; 3]) -> [u8; 4] {
+    let (r, g, b) = (r.max(0.0), g.max(0.0), b.max(0.0));
+    let brightest = r.max(g).max(b);
+    if !brightest.is_finite() || brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // brightest = mantissa * 2^exponent with mantissa in [0.5, 1).
+    let exponent = brightest.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f32.powi(exponent);
+    let channel = |c: f32| (c * scale).min(255.0) as u8;
+    [channel(r), channel(g), channel(b), (exponent + 128).clamp(0, 255) as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_rgbe([r, g, b, e]: [u8; 4]) -> [f32; 3] {
+        if e == 0 {
+            return [0.0; 3];
+        }
+        let scale = 2f32.powi(e as i32 - 128 - 8);
+        [r, g, b].map(|c| c as f32 * scale)
+    }
+
+    #[test]
+    fn values_above_one_survive_a_round_trip() {
+        let path = std::env::temp_dir().join(format!("lab4_hdr_{}.hdr", std::process::id()));
+        let path = path.to_str().unwrap();
+        save_hdr(path, 1, 1, &[[4.0, 0.0, 0.0]]).unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 1\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let pixel = from_rgbe(bytes[header.len()..].try_into().unwrap());
+        assert!(pixel[0] > 1.0);
+        assert!((pixel[0] - 4.0).abs() / 4.0 < 0.01, "red came back as {}", pixel[0]);
+        assert_eq!(pixel[1..], [0.0, 0.0]);
+    }
+}
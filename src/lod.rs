@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use nalgebra_glm::Vec3;
+use crate::vertex::Vertex;
+
+// Pre-built detail levels of one triangle list, finest first.
+pub struct LodMesh {
+    pub levels: Vec<Vec<Vertex>>,
+    pub radius: f32,
+}
+
+// Screen-space radius (in pixels) a body needs to keep each level; anything
+// smaller falls through to the next, coarser level.
+const LOD_PIXEL_THRESHOLDS: [f32; 2] = [80.0, 30.0];
+
+impl LodMesh {
+    // Builds `level_count` levels by clustering vertices onto progressively
+    // coarser grids and dropping the triangles that collapse.
+    pub fn build(base: &[Vertex], level_count: usize) -> Self {
+        let radius = base.iter().map(|v| v.position.magnitude()).fold(0.0, f32::max);
+        let mut levels = vec![base.to_vec()];
+
+        for level in 1..level_count.max(1) {
+            let cell_size = radius * 0.3 * (1 << (level - 1)) as f32;
+            levels.push(cluster_vertices(base, cell_size));
+        }
+
+        LodMesh { levels, radius }
+    }
+
+    pub fn select(&self, projected_radius: f32) -> usize {
+        let index = LOD_PIXEL_THRESHOLDS.iter()
+            .position(|&threshold| projected_radius >= threshold)
+            .unwrap_or(LOD_PIXEL_THRESHOLDS.len());
+        index.min(self.levels.len() - 1)
+    }
+
+    pub fn level(&self, index: usize) -> &[Vertex] {
+        &self.levels[index.min(self.levels.len() - 1)]
+    }
+}
+
+// Approximate on-screen radius in pixels of a sphere of `radius` at
+// `distance` from the eye, for a vertical field of view `fov`.
+pub fn projected_radius(radius: f32, distance: f32, fov: f32, viewport_height: f32) -> f32 {
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+    radius / (distance * (fov / 2.0).tan()) * viewport_height / 2.0
+}
+
+fn cluster_vertices(base: &[Vertex], cell_size: f32) -> Vec<Vertex> {
+    let cell_of = |p: &Vec3| (
+        (p.x / cell_size).floor() as i32,
+        (p.y / cell_size).floor() as i32,
+        (p.z / cell_size).floor() as i32,
+    );
+
+    let mut clusters: HashMap<(i32, i32, i32), (Vec3, Vec3, usize)> = HashMap::new();
+    for vertex in base {
+        let entry = clusters.entry(cell_of(&vertex.position)).or_insert((Vec3::zeros(), Vec3::zeros(), 0));
+        entry.0 += vertex.position;
+        entry.1 += vertex.normal;
+        entry.2 += 1;
+    }
+
+    let representative = |vertex: &Vertex| {
+        let (position_sum, normal_sum, count) = clusters[&cell_of(&vertex.position)];
+        let mut merged = vertex.clone();
+        merged.position = position_sum / count as f32;
+        if normal_sum.magnitude() > 1e-6 {
+            merged.normal = normal_sum.normalize();
+        }
+        (cell_of(&vertex.position), merged)
+    };
+
+    let mut vertices = Vec::new();
+    for tri in base.chunks(3).filter(|tri| tri.len() == 3) {
+        let (c0, v0) = representative(&tri[0]);
+        let (c1, v1) = representative(&tri[1]);
+        let (c2, v2) = representative(&tri[2]);
+        if c0 == c1 || c1 == c2 || c0 == c2 {
+            continue;
+        }
+        vertices.extend([v0, v1, v2]);
+    }
+
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    #[test]
+    fn distant_bodies_select_coarser_levels() {
+        let corner = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros());
+        let base = vec![corner(-1.0, -1.0), corner(1.0, -1.0), corner(0.0, 1.0)];
+        let lod = LodMesh::build(&base, 3);
+        let fov = 45.0_f32.to_radians();
+
+        let near = lod.select(projected_radius(lod.radius, 3.0, fov, 600.0));
+        let far = lod.select(projected_radius(lod.radius, 60.0, fov, 600.0));
+        assert_eq!(near, 0);
+        assert!(far > near);
+        assert_eq!(far, lod.levels.len() - 1);
+    }
+}
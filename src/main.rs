@@ -1,7 +1,8 @@
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat3, Mat4, look_at, perspective};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 use std::time::Duration;
 use std::f32::consts::PI;
+use std::ops::AddAssign;
 
 mod framebuffer;
 mod triangle;
@@ -11,24 +12,136 @@ mod color;
 mod fragment;
 mod shaders;
 mod camera;
+mod body;
+mod scene;
+mod post;
+mod texture;
+mod frame_limiter;
+mod quality;
+mod material;
+mod lod;
+mod stars;
+mod clipping;
+mod render_context;
+mod time_control;
+mod shadow;
+mod peeling;
+mod particles;
+mod msaa;
+mod validation;
+mod hdr;
+mod rng;
+mod recording;
 
 use crate::color::Color;
-use framebuffer::Framebuffer;
+use fragment::Fragment;
+use framebuffer::{BlendMode, ColorOrder, CoverageCurve, DepthMode, Framebuffer};
 use vertex::Vertex;
 use obj::Obj;
 use camera::Camera;
-use triangle::triangle;
-use shaders::{earth_shader,  jupiter_shader, mars_shader, 
-    moon_shader, sun_shader, vertex_shader, comet_shader, saturn_shader};
+use body::Body;
+use scene::Scene;
+use texture::{Filter, Texture, Wrap};
+use frame_limiter::FrameLimiter;
+use quality::QualityController;
+use material::{paint_roughness, MaterialPreset, MATERIAL_PRESETS};
+use lod::{LodMesh, projected_radius};
+use stars::{load_star_catalog, render_star_environment, render_stars, render_stars_through, Star};
+use clipping::clip_triangle;
+use render_context::RenderContext;
+use time_control::TimeControl;
+use shadow::ShadowMap;
+use peeling::{render_transparent_peeled, TransparentFragment};
+use particles::ParticleSystem;
+use msaa::{sample_offsets, MultisampleBuffer};
+use validation::validate;
+use rng::Rng;
+use recording::Recorder;
+use post::{post_process, composite_anaglyph, depth_of_field, draw_screen_grid, motion_blur, reconstruct_normals_from_depth, PostChain};
+use triangle::{geometric_normal, rasterize_line, screen_area, triangle, triangle_multisample, vertex_fragment, DisplayMode, MAX_SAMPLES, NormalSource};
+use shaders::{asteroid_shader, skybox_shader, earth_shader, jupiter_shader, mars_shader, 
+    moon_shader, sun_shader, vertex_shader, comet_shader, saturn_shader, uranus_shader, neptune_shader, corona_shader, create_normal_matrix, specular, Shaded, ShadingFrequency};
 use fastnoise_lite::{FastNoiseLite, NoiseType};
 
+#[derive(Default)]
+struct Settings {
+    show_depth_normals: bool,
+    depth_of_field: bool,
+    stereo: bool,
+    anaglyph: bool,
+    panorama: bool,
+    post_chain: PostChain,
+    vertex_emission_from_color: bool,
+    comet_tail_blend: BlendMode,
+    motion_blur: bool,
+    shading_frequency: ShadingFrequency,
+    texture_filter: Filter,
+    auto_orbit: bool,
+    normal_source: NormalSource,
+    color_order: ColorOrder,
+    shadows: bool,
+    shadow_kernel: usize,
+    display_mode: DisplayMode,
+    depth_peel_layers: usize,
+    msaa_samples: usize,
+    safe_mode: bool,
+    lod_bias: f32,
+    point_size: f32,
+    earth_rotation: f32,
+    texture_wrap: Wrap,
+    asteroid_belt: bool,
+    skybox: bool,
+    clip_offset: Option<f32>,
+    show_normals: bool,
+    screen_grid: bool,
+    render_scale: f32,
+    coverage_curve: CoverageCurve,
+    cull_backfaces: bool,
+}
+
 pub struct Uniforms<'a> {
     model_matrix: Mat4,
+    previous_model_matrix: Mat4,
+    // Last frame's projection * view, so camera motion shows up in velocity.
+    previous_view_projection: Mat4,
     view_matrix: Mat4,
     projection_matrix: Mat4,
     viewport_matrix: Mat4,
+    normal_matrix: Mat3,
+    sun_position: Vec3,
+    camera_position: Vec3,
     time: u32,
     noise: &'a FastNoiseLite, 
+    environment: &'a Texture,
+    vertex_emission_from_color: bool,
+    comet_tail_blend: BlendMode,
+    quality: u8,
+    material: &'a MaterialPreset,
+    min_triangle_area: f32,
+    shading_frequency: ShadingFrequency,
+    texture_filter: Filter,
+    normal_source: NormalSource,
+    shadow_map: Option<&'a ShadowMap>,
+    shadow_kernel: usize,
+    display_mode: DisplayMode,
+    depth_peel_layers: usize,
+    ambient: f32,
+    shader_transition: Option<(usize, f32)>,
+    msaa_samples: usize,
+    // Glow of the mesh being drawn as a multiple of its surface color, set
+    // per draw from the body or instance.
+    emission: f32,
+    lod_bias: f32,
+    point_size: f32,
+    earth_rotation: f32,
+    texture_wrap: Wrap,
+    instance_seed: f32,
+    depth_mode: DepthMode,
+    clip_plane: Option<Vec4>,
+    // Drops triangles facing away from the camera before clipping.
+    cull_backfaces: bool,
+    // Per-material `map_Ke` textures, looked up by each fragment's material.
+    emissive_maps: &'a [Option<&'a Texture>],
 }
 
 
@@ -75,16 +188,67 @@ fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
     transform_matrix * rotation_matrix
 }
 
+// The pipeline is right-handed throughout: world and view space have +y up
+// and the camera looking down -z, so points farther along the view direction
+// end up with larger NDC z (OpenGL-style, -1 at the near plane). Screen depth
+// is that NDC z, and the zbuffer keeps the smallest value.
 fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
     look_at(&eye, &center, &up)
 }
 
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
+// `fov` is the vertical field of view in radians. Note that glm takes the
+// aspect ratio first.
+fn create_perspective_matrix(fov: f32, window_width: f32, window_height: f32) -> Mat4 {
     let aspect_ratio = window_width / window_height;
-    perspective(fov, aspect_ratio, 0.1, 1000.0)
+    perspective(aspect_ratio, fov, NEAR_PLANE, FAR_PLANE)
+}
+
+// Asymmetric perspective projection with the given near-plane bounds, for
+// rendering one piece of a larger view. Same conventions as `perspective`:
+// right-handed, NDC z from -1 at `near` to 1 at `far`.
+fn create_perspective_offcenter(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+    Mat4::new(
+        2.0 * near / (right - left), 0.0, (right + left) / (right - left), 0.0,
+        0.0, 2.0 * near / (top - bottom), (top + bottom) / (top - bottom), 0.0,
+        0.0, 0.0, -(far + near) / (far - near), -2.0 * far * near / (far - near),
+        0.0, 0.0, -1.0, 0.0,
+    )
+}
+
+// Near-plane bounds (left, right, bottom, top) of tile (`column`, `row`) in a
+// `columns` x `rows` grid splitting the frustum of `fov` and `aspect_ratio`.
+// Row 0 is the top of the image. Every grid line comes from one expression,
+// so neighbouring tiles share their seam bit for bit.
+fn tile_frustum(fov: f32, aspect_ratio: f32, column: usize, row: usize, columns: usize, rows: usize) -> (f32, f32, f32, f32) {
+    let top = NEAR_PLANE * (fov / 2.0).tan();
+    let right = top * aspect_ratio;
+    let vertical = |i: usize| -right + 2.0 * right * i as f32 / columns as f32;
+    let horizontal = |i: usize| top - 2.0 * top * i as f32 / rows as f32;
+    (vertical(column), vertical(column + 1), horizontal(row + 1), horizontal(row))
+}
+
+// Debug-build check that depth grows away from the camera, catching a
+// handedness or sign slip in the view or projection matrices.
+fn debug_assert_depth_order(camera: &Camera, view_matrix: &Mat4, projection_matrix: &Mat4) {
+    if cfg!(debug_assertions) {
+        let forward = (camera.center - camera.eye).normalize();
+        let depth = |distance: f32| {
+            let point = camera.eye + forward * distance;
+            let clip = projection_matrix * view_matrix * point.push(1.0);
+            clip.z / clip.w
+        };
+        let (near, far) = (depth(1.0), depth(2.0));
+        assert!(near < far, "depth order inverted: near {} >= far {}", near, far);
+    }
+}
+
+// One side of the internal framebuffer for a window side at `render_scale`.
+fn render_size(size: usize, render_scale: f32) -> usize {
+    ((size as f32 * render_scale).round() as usize).max(1)
 }
 
+// Maps NDC to pixels with y flipped so +y points up on screen; depth passes
+// through unchanged.
 fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     Mat4::new(
         width / 2.0, 0.0, 0.0, width / 2.0,
@@ -94,97 +258,767 @@ fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     )
 }
 
+const NEAR_PLANE: f32 = 0.1;
+const FAR_PLANE: f32 = 1000.0;
+const CORONA_SCALE: f32 = 1.6;
+// Radians per frame while auto-orbit is on; one turn takes about 21 s at 60 FPS.
+const AUTO_ORBIT_SPEED: f32 = 0.005;
+const SHADOW_MAP_SIZE: usize = 512;
+const SHADOW_BIAS: f32 = 0.05;
+const INSPECTION_COLOR: u32 = 0xE0E0E0;
+const DEPTH_PEEL_LAYERS: usize = 4;
+const COMET_SHADER: usize = 6;
+const ASTEROID_SHADER: usize = 7;
+const ASTEROID_COUNT: usize = 200;
+const SKYBOX_SHADER: usize = 8;
+const URANUS_SHADER: usize = 9;
+const NEPTUNE_SHADER: usize = 10;
+// Far enough out to enclose the scene; AlwaysFar keeps it behind anyway.
+const SKYBOX_SCALE: f32 = 50.0;
+// NDC depth of the far plane.
+const FAR_DEPTH: f32 = 1.0;
+// World units per frame while moving the cutaway plane.
+const CLIP_PLANE_SPEED: f32 = 0.02;
+const NORMAL_LINE_COLOR: u32 = 0x40FF40;
+const NORMAL_LINE_LENGTH: f32 = 0.15;
+// Merge distance for near-duplicate vertices in imported meshes.
+const WELD_EPSILON: f32 = 1e-5;
+// Internal resolution as a fraction of the window, cycled with Backquote.
+const RENDER_SCALES: [f32; 4] = [1.0, 0.75, 0.5, 0.25];
+const SCREEN_GRID_SPACING: usize = 100;
+const SCREEN_GRID_COLOR: u32 = 0xFFFFFF;
+const SCREEN_GRID_OPACITY: f32 = 0.25;
+// One second at 60 FPS.
+const TIME_SEEK_STEP: i64 = 60;
+const SPECULAR_STRENGTH: f32 = 0.6;
+// About half a second at 60 FPS.
+const SHADER_TRANSITION_FRAMES: f32 = 30.0;
+const LOD_BIAS_STEP: f32 = 0.5;
+const MAX_LOD_BIAS: f32 = 8.0;
+const MAX_POINT_SIZE: f32 = 16.0;
+const POSTER_TILES: (usize, usize) = (2, 2);
+const BLOOM_THRESHOLD_STEP: f32 = 0.25;
+// One hour of Earth's day per key press.
+const EARTH_ROTATION_STEP: f32 = PI / 12.0;
+const ACCUMULATION_PERSISTENCE: f32 = 0.6;
+// Camera moves larger than this fraction of the viewing distance in one frame
+// count as a cut, and drop the accumulated motion blur history.
+const CAMERA_JUMP_FRACTION: f32 = 0.1;
+
+fn camera_jumped(previous: (Vec3, Vec3), camera: &Camera) -> bool {
+    let (eye, center) = previous;
+    let distance = (eye - center).magnitude().max(f32::EPSILON);
+    let moved = (camera.eye - eye).magnitude().max((camera.center - center).magnitude());
+    moved > distance * CAMERA_JUMP_FRACTION
+}
+
+// Draws the corona as a larger translucent shell around the sun. It runs after
+// the opaque pass, blends over what's behind it without writing depth, and
+// adds its glow to the emission buffer only where nothing nearer covers it.
+fn render_corona(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) {
+    let shell_uniforms = Uniforms {
+        model_matrix: uniforms.model_matrix * Mat4::new_scaling(CORONA_SCALE),
+        previous_model_matrix: uniforms.previous_model_matrix * Mat4::new_scaling(CORONA_SCALE),
+        ..*uniforms
+    };
+
+    for tri in vertex_array.chunks(3).filter(|tri| tri.len() == 3) {
+        let transformed = [
+            vertex_shader(&tri[0], &shell_uniforms),
+            vertex_shader(&tri[1], &shell_uniforms),
+            vertex_shader(&tri[2], &shell_uniforms),
+        ];
+        if transformed.iter().any(|vertex| vertex.is_clipped) {
+            continue;
+        }
+
+        let fragments = clip_triangle(&transformed, &uniforms.viewport_matrix)
+            .into_iter()
+            .flat_map(|[v0, v1, v2]| {
+                triangle(&v0, &v1, &v2, &uniforms.sun_position, framebuffer.width, framebuffer.height)
+            })
+            .collect::<Vec<_>>();
+
+        for fragment in fragments {
+            let shaded = corona_shader(&fragment, &shell_uniforms, 1.0 / CORONA_SCALE);
+            if shaded.opacity <= 0.0 {
+                continue;
+            }
+
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            framebuffer.add_emission(x, y, fragment.depth, shaded.emission.to_hex());
+            framebuffer.blend_point(BlendMode::Alpha, x, y, fragment.depth, shaded.color.to_hex(), shaded.opacity);
+        }
+    }
+}
+
+// Runs the selected body shader.
+fn shade_fragment(fragment: &Fragment, uniforms: &Uniforms, shader_index: usize) -> Shaded {
+    match shader_index {
+        0 => sun_shader(fragment, uniforms),
+        1 => earth_shader(fragment, uniforms),
+        2 => mars_shader(fragment, uniforms),
+        3 => jupiter_shader(fragment, uniforms),
+        4 => moon_shader(fragment, uniforms),
+        5 => saturn_shader(fragment, uniforms),
+        6 => comet_shader(fragment, uniforms),
+        ASTEROID_SHADER => asteroid_shader(fragment, uniforms),
+        SKYBOX_SHADER => skybox_shader(fragment, uniforms),
+        URANUS_SHADER => uranus_shader(fragment, uniforms),
+        NEPTUNE_SHADER => neptune_shader(fragment, uniforms),
+        _ => Shaded::opaque(Color::black()),
+    }
+}
+
+// While a shader switch is fading in, runs both the outgoing and incoming
+// shaders and blends their outputs by the transition's progress.
+fn shade_with_transition(fragment: &Fragment, uniforms: &Uniforms, shader_index: usize) -> Shaded {
+    match uniforms.shader_transition {
+        Some((from, t)) if from != shader_index => {
+            shade_fragment(fragment, uniforms, from).lerp(&shade_fragment(fragment, uniforms, shader_index), t)
+        }
+        _ => shade_fragment(fragment, uniforms, shader_index),
+    }
+}
+
+// Cross-fade state for switching body shaders, measured in animation time.
+struct ShaderTransition {
+    from: usize,
+    to: usize,
+    start: u32,
+}
+
+impl ShaderTransition {
+    fn new(shader_index: usize) -> Self {
+        ShaderTransition { from: shader_index, to: shader_index, start: 0 }
+    }
+
+    // Starts fading toward `shader_index` if it isn't already the target.
+    fn retarget(&mut self, shader_index: usize, time: u32) {
+        if shader_index != self.to {
+            self.from = self.to;
+            self.to = shader_index;
+            self.start = time;
+        }
+    }
+
+    // The outgoing shader and blend progress in [0, 1), or `None` once the
+    // fade has finished.
+    fn blend(&self, time: u32) -> Option<(usize, f32)> {
+        let t = time.saturating_sub(self.start) as f32 / SHADER_TRANSITION_FRAMES;
+        (self.from != self.to && t < 1.0).then_some((self.from, t))
+    }
+}
+
+// Tracks whether the last rendered frame is still correct. Every setting and
+// body change comes from input, so the frame only goes stale through input,
+// camera motion or a change of animation time; anything else that
+// invalidates it calls `invalidate`.
+struct FrameCache {
+    rendered_time: Option<u32>,
+    dirty: bool,
+}
+
+impl FrameCache {
+    fn new() -> Self {
+        FrameCache { rendered_time: None, dirty: true }
+    }
+
+    fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    // Whether the frame at `time` has to be rendered, or the cached one can be
+    // presented again.
+    fn needs_render(&self, time: u32) -> bool {
+        self.dirty || self.rendered_time != Some(time)
+    }
+
+    fn rendered(&mut self, time: u32) {
+        self.rendered_time = Some(time);
+        self.dirty = false;
+    }
+}
+
+// Counters for one `render` call. Culled triangles are those dropped before
+// rasterization: near-zero w, facing away with backface culling on, entirely
+// outside the view volume, or below the minimum screen area. Clipping can split a triangle, so submitted
+// triangles may outnumber the mesh's.
+#[derive(Debug, Clone, Copy, Default)]
+struct RenderStats {
+    triangles_submitted: usize,
+    triangles_culled: usize,
+    fragments_generated: usize,
+    fragments_written: usize,
+    shader_invocations: usize,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.triangles_submitted += other.triangles_submitted;
+        self.triangles_culled += other.triangles_culled;
+        self.fragments_generated += other.fragments_generated;
+        self.fragments_written += other.fragments_written;
+        self.shader_invocations += other.shader_invocations;
+    }
+}
+
+// One copy of a shared mesh: where it sits, the seed that varies its
+// surface (see `asteroid_shader`) and how much it glows.
+struct InstanceData {
+    model_matrix: Mat4,
+    seed: f32,
+    emission: f32,
+}
+
+// Draws the same vertex array once per instance, swapping only the model
+// matrix and seed. Instances don't move between frames, so only camera motion
+// blurs them, and the shadow map, which belongs to the main body, is off.
+fn render_instanced(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    instances: &[InstanceData],
+    shader_index: usize,
+    mut multisample: Option<&mut MultisampleBuffer>,
+) -> RenderStats {
+    let mut stats = RenderStats::default();
+    for instance in instances {
+        let Some(normal_matrix) = create_normal_matrix(&instance.model_matrix) else {
+            continue;
+        };
+        let instance_uniforms = Uniforms {
+            model_matrix: instance.model_matrix,
+            previous_model_matrix: instance.model_matrix,
+            normal_matrix,
+            instance_seed: instance.seed,
+            emissive_maps: &[],
+            shadow_map: None,
+            shader_transition: None,
+            emission: instance.emission,
+            ..*uniforms
+        };
+        stats += render_with(framebuffer, &instance_uniforms, vertex_array, shader_index, multisample.as_deref_mut());
+    }
+    stats
+}
+
+// Asteroids scattered through a flat ring between `inner` and `outer` around
+// the origin, each with its own size, spin and seed.
+fn asteroid_belt(count: usize, inner: f32, outer: f32, seed: u64) -> Vec<InstanceData> {
+    let mut rng = Rng::new(seed);
+    (0..count).map(|_| {
+        let angle = rng.range(0.0, 2.0 * PI);
+        let radius = rng.range(inner, outer);
+        let position = Vec3::new(radius * angle.cos(), rng.range(-0.05, 0.05) * outer, radius * angle.sin());
+        let rotation = rng.next_unit_vec3() * PI;
+        InstanceData {
+            model_matrix: create_model_matrix(position, rng.range(0.02, 0.06), rotation),
+            seed: rng.next_f32(),
+            emission: 0.0,
+        }
+    }).collect()
+}
+
 fn render(
     framebuffer: &mut Framebuffer,
     uniforms: &Uniforms,
     vertex_array: &[Vertex],
     shader_index: usize,
-) {
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+) -> RenderStats {
+    let mut multisample = (uniforms.msaa_samples > 1).then(|| MultisampleBuffer::new(framebuffer, uniforms.msaa_samples));
+    render_with(framebuffer, uniforms, vertex_array, shader_index, multisample.as_mut())
+}
+
+// `render` drawing through a caller-owned multisample buffer, so passes that
+// run many times a frame share one allocation. The buffer is only used when
+// `uniforms` asks for MSAA.
+fn render_with(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    shader_index: usize,
+    multisample: Option<&mut MultisampleBuffer>,
+) -> RenderStats {
+    let mut stats = RenderStats::default();
+    let mut transformed_vertices: Vec<Vertex> = vertex_array
+        .iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+
+    if uniforms.normal_source == NormalSource::Geometric {
+        for tri in transformed_vertices.chunks_exact_mut(3) {
+            let normal = geometric_normal(&tri[0], &tri[1], &tri[2]);
+            for vertex in tri.iter_mut() {
+                vertex.transformed_normal = normal;
+            }
+        }
+    }
+
+    if uniforms.shading_frequency == ShadingFrequency::PerVertex {
+        for transformed in transformed_vertices.iter_mut() {
+            let mut fragment = vertex_fragment(transformed, &uniforms.sun_position);
+            uniforms.material.prepare(&mut fragment);
+            let shaded = shade_with_transition(&fragment, uniforms, shader_index);
+            transformed.shaded_color = shaded.color;
+            transformed.shaded_emission = shaded.emission;
+            transformed.shaded_opacity = shaded.opacity;
+        }
+        stats.shader_invocations += transformed_vertices.len();
     }
 
     let mut triangles = Vec::new();
     for i in (0..transformed_vertices.len()).step_by(3) {
         if i + 2 < transformed_vertices.len() {
-            triangles.push([
+            let tri = [
                 transformed_vertices[i].clone(),
                 transformed_vertices[i + 1].clone(),
                 transformed_vertices[i + 2].clone(),
-            ]);
+            ];
+            if tri.iter().any(|vertex| vertex.is_clipped) {
+                stats.triangles_culled += 1;
+                continue;
+            }
+            if uniforms.cull_backfaces {
+                let normal = geometric_normal(&tri[0], &tri[1], &tri[2]);
+                if normal.dot(&(uniforms.camera_position - tri[0].world_position)) <= 0.0 {
+                    stats.triangles_culled += 1;
+                    continue;
+                }
+            }
+            let clipped = clip_triangle(&tri, &uniforms.viewport_matrix);
+            if clipped.is_empty() {
+                stats.triangles_culled += 1;
+            }
+            triangles.extend(clipped);
         }
     }
 
+    if uniforms.display_mode != DisplayMode::Solid {
+        render_inspection(framebuffer, uniforms.display_mode, uniforms.point_size, &triangles);
+        stats.triangles_submitted = triangles.len();
+        return stats;
+    }
+
+    let mut multisample = multisample.filter(|_| uniforms.msaa_samples > 1);
+    if let Some(buffer) = multisample.as_deref_mut() {
+        buffer.reset(framebuffer);
+    }
     let mut fragments = Vec::new();
     for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
+        let area = screen_area(
+            &tri[0].transformed_position,
+            &tri[1].transformed_position,
+            &tri[2].transformed_position,
+        );
+        if area < uniforms.min_triangle_area {
+            stats.triangles_culled += 1;
+            continue;
+        }
+        stats.triangles_submitted += 1;
+
+        if multisample.is_some() {
+            fragments.extend(triangle_multisample(
+                &tri[0],
+                &tri[1],
+                &tri[2],
+                &uniforms.sun_position,
+                framebuffer.width,
+                framebuffer.height,
+                sample_offsets(uniforms.msaa_samples),
+            ).into_iter().map(|(fragment, coverage)| (fragment, Some(coverage))));
+        } else {
+            fragments.extend(triangle(
+                &tri[0],
+                &tri[1],
+                &tri[2],
+                &uniforms.sun_position,
+                framebuffer.width,
+                framebuffer.height,
+            ).into_iter().map(|fragment| (fragment, None)));
+        }
     }
 
-    for fragment in fragments {
+    // Translucent tail fragments and their glow are deferred until every
+    // opaque fragment is in and any multisample buffer is resolved, which
+    // would otherwise overwrite them. Alpha blending is then depth peeled
+    // instead of applied in rasterization order.
+    let peel = uniforms.depth_peel_layers > 0 && uniforms.comet_tail_blend == BlendMode::Alpha;
+    let mut transparent = Vec::new();
+    let mut tail_emission = Vec::new();
+    stats.fragments_generated = fragments.len();
+
+    for (mut fragment, mut coverage) in fragments {
+        if let Some(plane) = uniforms.clip_plane {
+            if plane.xyz().dot(&fragment.world_position) + plane.w < 0.0 {
+                continue;
+            }
+        }
+        if uniforms.depth_mode == DepthMode::AlwaysFar {
+            fragment.depth = FAR_DEPTH;
+            if let Some(coverage) = coverage.as_mut() {
+                coverage.depths = [FAR_DEPTH; MAX_SAMPLES];
+            }
+        }
+        uniforms.material.prepare(&mut fragment);
         let x = fragment.position.x as usize;
         let y = fragment.position.y as usize;
     
         if x < framebuffer.width && y < framebuffer.height {
-            let (color, emission) = match shader_index {
-                0 => {
-                    let color = sun_shader(uniforms);
-                    (color, color.to_hex())  
+            let Shaded { color, emission, opacity: tail_opacity } = match uniforms.shading_frequency {
+                ShadingFrequency::PerVertex => Shaded {
+                    color: fragment.shaded_color,
+                    emission: fragment.shaded_emission,
+                    opacity: fragment.shaded_opacity,
+                },
+                ShadingFrequency::PerFragment => {
+                    stats.shader_invocations += 1;
+                    shade_with_transition(&fragment, uniforms, shader_index)
                 }
-                1 => (earth_shader(&fragment, uniforms), 0),
-                2 => (mars_shader(&fragment, uniforms), 0),
-                3 => (jupiter_shader(&fragment, uniforms), 0),
-                4 => (moon_shader(&fragment, uniforms), 0),
-                5 => (saturn_shader(&fragment, uniforms), 0),
-                6 => (comet_shader(&fragment, uniforms), 0),
-                _ => (Color::black(), 0),
             };
-    
-            framebuffer.set_current_color(color.to_hex());
-    
-            if emission != 0 {
-                framebuffer.point_with_emission(x, y, fragment.depth, emission);
+            if tail_opacity > 0.0 {
+                // The tail's glow is weighted by how much of it shows.
+                tail_emission.push((emission * tail_opacity).to_hex());
+                transparent.push(TransparentFragment { x, y, depth: fragment.depth, color: color.to_hex(), alpha: tail_opacity });
+                continue;
+            }
+
+            let color = uniforms.material.apply(color, &fragment, uniforms.noise);
+
+            // Vertex color luminance doubles as an artist-painted glow mask.
+            let emission = if emission.is_black() && uniforms.vertex_emission_from_color {
+                color * fragment.vertex_color.luminance()
+            } else {
+                emission
+            };
+            let emission = if uniforms.emission > 0.0 {
+                emission + color * uniforms.emission
+            } else {
+                emission
+            };
+            // `map_Ke` texels glow on their own, independent of lighting.
+            let map = fragment.material_id.and_then(|id| uniforms.emissive_maps.get(id).copied().flatten());
+            let emission = match map {
+                Some(map) => emission + map.sample(
+                    fragment.tex_coords.x,
+                    fragment.tex_coords.y,
+                    uniforms.texture_filter,
+                    uniforms.texture_wrap,
+                    uniforms.lod_bias,
+                ),
+                None => emission,
+            };
+
+            let color = if shader_index == 0 {
+                color
             } else {
-                framebuffer.point_with_emission(x, y, fragment.depth, 0);
+                color + Color::new(255, 255, 255) * (specular(&fragment, uniforms) * SPECULAR_STRENGTH)
+            };
+
+            let color = match uniforms.shadow_map {
+                Some(shadow_map) => color * shadow_map.shadow_factor(&fragment.world_position, SHADOW_BIAS, uniforms.shadow_kernel),
+                None => color,
+            };
+            let color = color * fragment.ao;
+            let written = match (multisample.as_mut(), coverage) {
+                (Some(buffer), Some(coverage)) => buffer.write(x, y, &coverage, color.to_hex(), emission.to_hex(), fragment.velocity),
+                _ => {
+                    framebuffer.set_current_color(color.to_hex());
+                    framebuffer.set_current_velocity(fragment.velocity);
+                    framebuffer.point_with_emission(x, y, fragment.depth, emission.to_hex())
+                }
+            };
+            if written {
+                stats.fragments_written += 1;
             }
         }
     }
-    
+
+    if let Some(buffer) = multisample {
+        buffer.resolve(framebuffer);
+    }
+
+    for (fragment, &emission) in transparent.iter().zip(&tail_emission) {
+        framebuffer.add_emission(fragment.x, fragment.y, fragment.depth, emission);
+    }
+    if peel && !transparent.is_empty() {
+        render_transparent_peeled(framebuffer, &transparent, uniforms.depth_peel_layers);
+    } else if !peel {
+        for fragment in &transparent {
+            framebuffer.blend_point(uniforms.comet_tail_blend, fragment.x, fragment.y, fragment.depth, fragment.color, fragment.alpha);
+        }
+    }
+
+    if shader_index == 0 {
+        render_corona(framebuffer, uniforms, vertex_array);
+    }
+
+    stats
 }
-fn post_process(framebuffer: &mut Framebuffer) {
-    for (pixel, emission) in framebuffer.buffer.iter_mut().zip(&framebuffer.emission_buffer) {
-        if *emission != 0 {
-            *pixel = blend_emission(*pixel, *emission);
+
+// Unshaded, depth-tested edges or vertices of already clipped triangles, for
+// looking at mesh structure. Point mode draws each vertex as a disk
+// `point_size` pixels across, or a single pixel when the size is below one.
+fn render_inspection(framebuffer: &mut Framebuffer, mode: DisplayMode, point_size: f32, triangles: &[[Vertex; 3]]) {
+    framebuffer.set_current_color(INSPECTION_COLOR);
+    framebuffer.set_current_velocity(Vec2::zeros());
+    for tri in triangles {
+        match mode {
+            DisplayMode::Wireframe => {
+                for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+                    let (a, b) = (&tri[a].transformed_position, &tri[b].transformed_position);
+                    for sample in rasterize_line(a, b, framebuffer.width, framebuffer.height) {
+                        framebuffer.point_with_emission(sample.x, sample.y, sample.depth, 0);
+                    }
+                }
+            }
+            DisplayMode::Point => {
+                for vertex in tri {
+                    let p = vertex.transformed_position;
+                    if point_size >= 1.0 {
+                        framebuffer.draw_sprite(p.x, p.y, point_size * 0.5, INSPECTION_COLOR, p.z);
+                    } else if p.x >= 0.0 && p.y >= 0.0 {
+                        framebuffer.point_with_emission(p.x as usize, p.y as usize, p.z, 0);
+                    }
+                }
+            }
+            DisplayMode::Solid => {}
         }
     }
 }
 
-fn blend_emission(color: u32, emission: u32) -> u32 {
-    let r1 = (color >> 16) & 0xFF;
-    let g1 = (color >> 8) & 0xFF;
-    let b1 = color & 0xFF;
+// Debug overlay: a depth-tested segment from every vertex along its
+// world-space normal, `length` world units long.
+fn render_normals(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], length: f32) {
+    let view_projection = uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix;
+    framebuffer.set_current_color(NORMAL_LINE_COLOR);
+    framebuffer.set_current_velocity(Vec2::zeros());
+    for vertex in vertex_array {
+        let transformed = vertex_shader(vertex, uniforms);
+        if transformed.is_clipped {
+            continue;
+        }
+        let tip = view_projection * (transformed.world_position + transformed.transformed_normal.normalize() * length).push(1.0);
+        if tip.w <= f32::EPSILON || transformed.clip_position.w <= f32::EPSILON {
+            continue;
+        }
+        let tip = Vec3::new(tip.x / tip.w, tip.y / tip.w, tip.z / tip.w);
+        for sample in rasterize_line(&transformed.transformed_position, &tip, framebuffer.width, framebuffer.height) {
+            framebuffer.point_with_emission(sample.x, sample.y, sample.depth, 0);
+        }
+    }
+}
 
-    let r2 = (emission >> 16) & 0xFF;
-    let g2 = (emission >> 8) & 0xFF;
-    let b2 = emission & 0xFF;
+// View matrix for an eye shifted sideways along the camera's right vector,
+// keeping the view direction parallel to the original camera.
+fn create_eye_view_matrix(camera: &Camera, offset: f32) -> Mat4 {
+    let right = (camera.center - camera.eye).cross(&camera.up).normalize();
+    let shift = right * offset;
+    create_view_matrix(camera.eye + shift, camera.center + shift, camera.up)
+}
 
-    let blend = |c1, c2| ((c1 as f32 * 0.8) + (c2 as f32 * 0.2)).min(255.0) as u32;
+fn render_stereo(
+    framebuffer: &mut Framebuffer,
+    camera: &Camera,
+    eye_separation: f32,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    shader_index: usize,
+) {
+    let half_width = framebuffer.width / 2;
+    let height = framebuffer.height;
+    let projection_matrix = create_perspective_matrix(camera.fov, half_width as f32, height as f32);
 
-    let r = blend(r1, r2);
-    let g = blend(g1, g2);
-    let b = blend(b1, b2);
+    for (side, offset) in [(0, -0.5), (1, 0.5)] {
+        let x0 = side * half_width;
+        // The eyes keep no history of their own, so only object motion blurs.
+        let view_matrix = create_eye_view_matrix(camera, eye_separation * offset);
+        let eye_uniforms = Uniforms {
+            previous_view_projection: projection_matrix * view_matrix,
+            view_matrix,
+            projection_matrix,
+            viewport_matrix: Mat4::new_translation(&Vec3::new(x0 as f32, 0.0, 0.0))
+                * create_viewport_matrix(half_width as f32, height as f32),
+            ..*uniforms
+        };
+
+        framebuffer.set_scissor(Some((x0, 0, x0 + half_width, height)));
+        render(framebuffer, &eye_uniforms, vertex_array, shader_index);
+    }
 
-    (r << 16) | (g << 8) | b
+    framebuffer.set_scissor(None);
+}
+
+fn render_anaglyph(
+    framebuffer: &mut Framebuffer,
+    camera: &Camera,
+    stars: &[Star],
+    eye_separation: f32,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    shader_index: usize,
+) {
+    let mut context = RenderContext::new(framebuffer);
+    let eyes = [-eye_separation * 0.5, eye_separation * 0.5].map(|offset| {
+        let view_matrix = create_eye_view_matrix(camera, offset);
+        let eye_uniforms = Uniforms {
+            previous_view_projection: uniforms.projection_matrix * view_matrix,
+            view_matrix,
+            ..*uniforms
+        };
+        let target = context.push_target();
+        // Stars sit at infinity, so both eyes see the same sky and the
+        // composite keeps it uncolored.
+        let id = target.current_id();
+        target.set_current_id(None);
+        render_stars(target, camera, stars);
+        target.set_current_id(id);
+        render(target, &eye_uniforms, vertex_array, shader_index);
+        post_process(target);
+        context.pop_target()
+    });
+
+    // The right eye keeps its depth, emission and id buffers so picking still
+    // works; only its color is replaced by the composite.
+    if let [Some(left), Some(mut right)] = eyes {
+        right.buffer = composite_anaglyph(&left.buffer, &right.buffer);
+        *context.current() = right;
+    }
+}
+
+// Renders the view of `uniforms` as a `columns` x `rows` grid of
+// `tile_width` x `tile_height` tiles, each through its own off-center
+// frustum, and stitches them into one large framebuffer. The frustum is split
+// by the poster's own aspect ratio, so passing the on-screen size per tile
+// gives the on-screen framing at higher resolution. Stars are drawn into
+// every tile through the same projection; post-processing is left out, since
+// blurs would not cross tile edges.
+#[allow(clippy::too_many_arguments)]
+fn render_tiled(
+    scene: &Scene,
+    stars: &[Star],
+    (tile_width, tile_height): (usize, usize),
+    (columns, rows): (usize, usize),
+    fov: f32,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    shader_index: usize,
+) -> Framebuffer {
+    let mut poster = Framebuffer::new(columns * tile_width, rows * tile_height);
+    scene.apply(&mut poster);
+    poster.clear();
+    let aspect_ratio = poster.width as f32 / poster.height as f32;
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let mut tile = Framebuffer::new(tile_width, tile_height);
+            tile.clear();
+            // Start from the poster's background so gradients run across the
+            // whole image rather than restarting in every tile.
+            for y in 0..tile_height {
+                let start = (row * tile_height + y) * poster.width + column * tile_width;
+                tile.buffer[y * tile_width..(y + 1) * tile_width].copy_from_slice(&poster.buffer[start..start + tile_width]);
+            }
+
+            let (left, right, bottom, top) = tile_frustum(fov, aspect_ratio, column, row, columns, rows);
+            let projection_matrix = create_perspective_offcenter(left, right, bottom, top, NEAR_PLANE, FAR_PLANE);
+            let tile_uniforms = Uniforms {
+                previous_view_projection: projection_matrix * uniforms.view_matrix,
+                projection_matrix,
+                viewport_matrix: create_viewport_matrix(tile_width as f32, tile_height as f32),
+                ..*uniforms
+            };
+            render_stars_through(&mut tile, &tile_uniforms.view_matrix, &tile_uniforms.projection_matrix, stars);
+            render(&mut tile, &tile_uniforms, vertex_array, shader_index);
+
+            for y in 0..tile_height {
+                let start = (row * tile_height + y) * poster.width + column * tile_width;
+                let source = y * tile_width..(y + 1) * tile_width;
+                poster.buffer[start..start + tile_width].copy_from_slice(&tile.buffer[source.clone()]);
+                poster.alpha_buffer[start..start + tile_width].copy_from_slice(&tile.alpha_buffer[source]);
+            }
+        }
+    }
+    poster
+}
+
+// Renders the six cube faces around the camera eye and resamples them into an
+// equirectangular image of `width` x `width / 2`. The horizontal center looks
+// down +X and the top row is straight up.
+fn render_panorama(
+    scene: &Scene,
+    width: usize,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    shader_index: usize,
+) -> Vec<u32> {
+    let height = width / 2;
+    let face_size = (width / 4).max(1);
+    let eye = uniforms.camera_position;
+    let faces = [
+        (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+        (Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+        (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0)),
+    ];
+
+    let face_buffers: Vec<Vec<u32>> = faces.iter().map(|(forward, up)| {
+        let mut face = Framebuffer::new(face_size, face_size);
+        scene.apply(&mut face);
+        face.clear();
+        let view_matrix = create_view_matrix(eye, eye + forward, *up);
+        let projection_matrix = create_perspective_matrix(PI / 2.0, face_size as f32, face_size as f32);
+        let face_uniforms = Uniforms {
+            previous_view_projection: projection_matrix * view_matrix,
+            view_matrix,
+            projection_matrix,
+            viewport_matrix: create_viewport_matrix(face_size as f32, face_size as f32),
+            ..*uniforms
+        };
+        render(&mut face, &face_uniforms, vertex_array, shader_index);
+        post_process(&mut face);
+        face.buffer
+    }).collect();
+
+    let mut panorama = vec![0; width * height];
+    for py in 0..height {
+        let elevation = (0.5 - (py as f32 + 0.5) / height as f32) * PI;
+        for px in 0..width {
+            let azimuth = ((px as f32 + 0.5) / width as f32 - 0.5) * 2.0 * PI;
+            let direction = Vec3::new(
+                elevation.cos() * azimuth.cos(),
+                elevation.sin(),
+                elevation.cos() * azimuth.sin(),
+            );
+
+            let (face_index, (forward, up)) = faces.iter().enumerate()
+                .max_by(|(_, (a, _)), (_, (b, _))| direction.dot(a).total_cmp(&direction.dot(b)))
+                .unwrap();
+            let right = forward.cross(up);
+            let depth = direction.dot(forward);
+            let x = direction.dot(&right) / depth;
+            let y = direction.dot(up) / depth;
+
+            let fx = (((x + 1.0) * 0.5 * face_size as f32) as usize).min(face_size - 1);
+            let fy = (((1.0 - y) * 0.5 * face_size as f32) as usize).min(face_size - 1);
+            panorama[py * width + px] = face_buffers[face_index][fy * face_size + fx];
+        }
+    }
+
+    panorama
 }
 
 fn main() {
     let window_width = 800;
     let window_height = 800;
-    let framebuffer_width = 800;
-    let framebuffer_height = 800;  
+    let mut framebuffer_width = window_width;
+    let mut framebuffer_height = window_height;
 
-    let frame_delay = Duration::from_millis(16);
+    let mut frame_limiter = FrameLimiter::new(Duration::from_millis(16));
+    let mut quality = QualityController::new(frame_limiter.target());
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
     let mut window = Window::new(
@@ -198,11 +1032,26 @@ fn main() {
     window.set_position(500, 500);
     window.update();
 
-    framebuffer.set_background_color(0x333355);
+    let scene = Scene::load("assets/scenes/default.json").expect("Failed to load scene");
+    scene.apply(&mut framebuffer);
+    let sun_position = scene.sun_position();
+
+    let stars = load_star_catalog("assets/stars.csv").expect("Failed to load star catalog");
+
+    // Equirectangular environment for reflections: the scene's map if it has
+    // one, otherwise the background and star field rendered once offscreen.
+    let environment = scene.environment_texture().unwrap_or_else(|| {
+        let mut environment_buffer = Framebuffer::new(256, 128);
+        scene.apply(&mut environment_buffer);
+        environment_buffer.clear();
+        render_star_environment(&mut environment_buffer, &stars);
+        environment_buffer.as_texture()
+    });
 
-    let translation = Vec3::new(0.0, 0.0, 0.0);
-    let rotation = Vec3::new(0.0, 0.0, 0.0);
-    let scale = 1.0;
+    let mut body = Body::new(0, Vec3::new(0.0, 0.0, 0.0), 1.0)
+        .with_spin(23.5_f32.to_radians(), 0.01)
+        .with_orbit(scene.orbit)
+        .with_emission(scene.emission);
 
     let mut camera = Camera::new(
         Vec3::new(0.0, 0.0, 5.0),
@@ -210,26 +1059,139 @@ fn main() {
         Vec3::new(0.0, 1.0, 0.0),
     );
 
-    let obj = Obj::load("assets/models/sphere-1.obj").expect("Failed to load obj");
-    let vertex_arrays = obj.get_vertex_array();
-
-    let mut time = 0;
+    let mut obj = Obj::load("assets/models/sphere-1.obj").expect("Failed to load obj");
+    obj.weld(WELD_EPSILON);
+    println!("Loaded sphere-1.obj: {}", obj.stats());
+    obj.bake_ao(8);
     let noise = create_noise();
-    let mut shader_index = 0;
+    let mut base_vertices = obj.get_vertex_array();
+    paint_roughness(&mut base_vertices, &noise);
+    let lod_mesh = LodMesh::build(&base_vertices, 3);
+    let emissive_maps = obj.emissive_maps();
+
+    let mut time_control = TimeControl::new();
+    // `--time N` starts the animation at frame N, for reproducing a moment.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--time") {
+        match args.get(index + 1).map(|value| value.parse::<u32>()) {
+            Some(Ok(time)) => time_control.set_time(time),
+            _ => eprintln!("warning: --time expects a frame number"),
+        }
+    }
+    let mut settings = Settings {
+        shadow_kernel: 3,
+        msaa_samples: 1,
+        render_scale: 1.0,
+        ..Settings::default()
+    };
+    let mut mouse_was_down = false;
+    let mut pan_anchor: Option<(f32, f32)> = None;
+    let mut previous_model_matrix = None;
+    let mut previous_view_projection = None;
+    // Snapshot taken when the panorama view is switched on, with the width
+    // it was rendered at.
+    let mut panorama: Option<(usize, Vec<u32>)> = None;
+    let mut coma = ParticleSystem::new(body.translation, lod_mesh.radius * body.scale);
+    let mut coma_time = 0;
+    let mut shader_transition = ShaderTransition::new(body.shader_index);
+    let mut previous_camera = (camera.eye, camera.center);
+    let mut frame_cache = FrameCache::new();
+    let belt = asteroid_belt(ASTEROID_COUNT, 2.2, 3.0, 11);
+    let mut recorder = Recorder::new("recording");
 
     while window.is_open() {
+        frame_limiter.begin();
         if window.is_key_down(Key::Escape) {
             break;
         }
+        if window.is_key_pressed(Key::R, KeyRepeat::No) {
+            camera.reset();
+            camera.focus(body.translation);
+            time_control.reset();
+            shader_transition = ShaderTransition::new(body.shader_index);
+        }
+        if window.is_key_pressed(Key::Space, KeyRepeat::No) {
+            time_control.toggle_pause();
+        }
+        if window.is_key_pressed(Key::Period, KeyRepeat::Yes) {
+            time_control.step();
+        }
+        if window.is_key_pressed(Key::Slash, KeyRepeat::Yes) {
+            time_control.seek(TIME_SEEK_STEP);
+            println!("Time: {}", time_control.time());
+        }
+        if window.is_key_pressed(Key::Backslash, KeyRepeat::Yes) {
+            time_control.seek(-TIME_SEEK_STEP);
+            println!("Time: {}", time_control.time());
+        }
 
-        time += 1;
-        handle_input(&window, &mut camera, &mut shader_index);
+        time_control.tick();
+        let time = time_control.time();
+        handle_input(&window, &mut camera, &mut body, &mut settings);
+        // Middle-drag pans; the camera moves against the drag so the scene
+        // follows the cursor.
+        let mouse = window.get_mouse_pos(MouseMode::Discard).filter(|_| window.get_mouse_down(MouseButton::Middle));
+        if let (Some((x, y)), Some((last_x, last_y))) = (mouse, pan_anchor) {
+            camera.pan(-(x - last_x) / window_height as f32, -(y - last_y) / window_height as f32);
+        }
+        pan_anchor = mouse;
+        shader_transition.retarget(body.shader_index, time);
+        if settings.auto_orbit {
+            camera.auto_orbit(AUTO_ORBIT_SPEED, 1.0);
+        }
+
+        let focus = if body.shader_index == 0 { sun_position } else { Vec3::zeros() };
+        if body.translation != focus {
+            body.translation = focus;
+            camera.focus(focus);
+        }
+        if !settings.motion_blur || camera_jumped(previous_camera, &camera) {
+            framebuffer.clear_accum();
+        }
+        previous_camera = (camera.eye, camera.center);
+
+        // The internal resolution follows `render_scale`; the frame is
+        // upscaled back to the window on present.
+        let scaled = |size: usize| render_size(size, settings.render_scale);
+        if (scaled(window_width), scaled(window_height)) != (framebuffer_width, framebuffer_height) {
+            framebuffer_width = scaled(window_width);
+            framebuffer_height = scaled(window_height);
+            framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+            scene.apply(&mut framebuffer);
+            frame_cache.invalidate();
+            println!("Render scale: {} ({}x{})", settings.render_scale, framebuffer_width, framebuffer_height);
+        }
+
+        // With no input, a still camera and frozen time the last frame is
+        // presented again instead of re-rendered.
+        let input = !window.get_keys().is_empty() || window.get_mouse_down(MouseButton::Left);
+        if input || camera.has_changed {
+            frame_cache.invalidate();
+        }
+        if !frame_cache.needs_render(time) {
+            if !present_frame(&mut window, &framebuffer, &mut recorder, settings.color_order, (window_width, window_height)) {
+                break;
+            }
+            frame_limiter.end_and_wait();
+            continue;
+        }
+        camera.has_changed = false;
+        frame_cache.rendered(time);
 
         framebuffer.clear();
+        framebuffer.debug_assert_all_infinity();
+        framebuffer.set_coverage_curve(settings.coverage_curve);
+        render_stars(&mut framebuffer, &camera, &stars);
 
-        let model_matrix = create_model_matrix(translation, scale, rotation);
+        let body_position = body.position(time);
+        let model_matrix = create_model_matrix(body_position, body.scale, body.rotation(time));
+        let normal_matrix = create_normal_matrix(&model_matrix).unwrap_or_else(|| {
+            eprintln!("warning: model matrix is not invertible, falling back to identity normal matrix");
+            Mat3::identity()
+        });
         let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
         let projection_matrix = create_perspective_matrix(
+            camera.fov,
             window_width as f32,
             window_height as f32,
         );
@@ -237,40 +1199,422 @@ fn main() {
             framebuffer_width as f32,
             framebuffer_height as f32,
         );
+        debug_assert_depth_order(&camera, &view_matrix, &projection_matrix);
+
+        let screen_radius = projected_radius(
+            lod_mesh.radius * body.scale,
+            (camera.eye - body_position).magnitude(),
+            camera.fov,
+            framebuffer_height as f32,
+        );
+        let vertex_arrays = lod_mesh.level(lod_mesh.select(screen_radius));
+
+        // The sun lights everything else, so it never receives shadows.
+        let shadow_map = (settings.shadows && body.shader_index != 0).then(|| {
+            ShadowMap::render(
+                vertex_arrays,
+                &model_matrix,
+                sun_position,
+                body_position,
+                lod_mesh.radius * body.scale * 1.2,
+                SHADOW_MAP_SIZE,
+            )
+        });
 
         let uniforms = Uniforms {
             model_matrix,
+            previous_model_matrix: previous_model_matrix.unwrap_or(model_matrix),
+            previous_view_projection: previous_view_projection.unwrap_or(projection_matrix * view_matrix),
             view_matrix,
             projection_matrix,
             viewport_matrix,
+            normal_matrix,
+            sun_position,
+            camera_position: camera.eye,
             time,
             noise: &noise,  
+            environment: &environment,
+            vertex_emission_from_color: settings.vertex_emission_from_color,
+            emission: body.emission,
+            comet_tail_blend: settings.comet_tail_blend,
+            quality: quality.level(),
+            material: &MATERIAL_PRESETS[body.material],
+            min_triangle_area: 0.5,
+            shading_frequency: settings.shading_frequency,
+            texture_filter: settings.texture_filter,
+            texture_wrap: settings.texture_wrap,
+            lod_bias: settings.lod_bias,
+            normal_source: settings.normal_source,
+            shadow_map: shadow_map.as_ref(),
+            shadow_kernel: settings.shadow_kernel,
+            display_mode: settings.display_mode,
+            point_size: settings.point_size,
+            earth_rotation: settings.earth_rotation,
+            instance_seed: 0.0,
+            emissive_maps: &emissive_maps,
+            depth_mode: DepthMode::Normal,
+            // Offsets are measured from the body's center, so the cut
+            // follows it along its orbit.
+            cull_backfaces: settings.cull_backfaces,
+            clip_plane: settings.clip_offset.map(|offset| Vec4::new(1.0, 0.0, 0.0, -(body_position.x + offset))),
+            depth_peel_layers: settings.depth_peel_layers,
+            msaa_samples: settings.msaa_samples,
+            ambient: scene.ambient.clamp(0.0, 1.0),
+            shader_transition: shader_transition.blend(time),
+        };
+
+        let validation = if settings.safe_mode { validate(&uniforms, vertex_arrays) } else { Ok(()) };
+
+        let stats = if let Err(err) = validation {
+            eprintln!("warning: skipping frame: {}", err);
+            None
+        } else if settings.anaglyph {
+            framebuffer.set_current_id(Some(0));
+            render_anaglyph(&mut framebuffer, &camera, &stars, 0.2, &uniforms, vertex_arrays, body.shader_index);
+            None
+        } else if settings.stereo {
+            framebuffer.set_current_id(Some(0));
+            render_stereo(&mut framebuffer, &camera, 0.2, &uniforms, vertex_arrays, body.shader_index);
+            None
+        } else {
+            // One multisample buffer serves every pass of the frame.
+            let mut multisample = (uniforms.msaa_samples > 1).then(|| MultisampleBuffer::new(&framebuffer, uniforms.msaa_samples));
+            if settings.skybox {
+                let skybox_matrix = create_model_matrix(camera.eye, SKYBOX_SCALE, Vec3::zeros());
+                let skybox_uniforms = Uniforms {
+                    model_matrix: skybox_matrix,
+                    previous_model_matrix: skybox_matrix,
+                    normal_matrix: Mat3::identity(),
+                    depth_mode: DepthMode::AlwaysFar,
+                    clip_plane: None,
+                    // The camera sits inside the skybox.
+                    cull_backfaces: false,
+                    emissive_maps: &[],
+                    material: &MATERIAL_PRESETS[0],
+                    shadow_map: None,
+                    shader_transition: None,
+                    emission: 0.0,
+                    ..uniforms
+                };
+                let coarsest = lod_mesh.level(lod_mesh.levels.len() - 1);
+                render_with(&mut framebuffer, &skybox_uniforms, coarsest, SKYBOX_SHADER, multisample.as_mut());
+            }
+            // Only the body is tagged for picking; sky and asteroids stay
+            // untagged so clicking them leaves the camera alone.
+            framebuffer.set_current_id(Some(0));
+            let mut stats = render_with(&mut framebuffer, &uniforms, vertex_arrays, body.shader_index, multisample.as_mut());
+            if settings.show_normals {
+                render_normals(&mut framebuffer, &uniforms, vertex_arrays, NORMAL_LINE_LENGTH * body.scale);
+            }
+            if settings.asteroid_belt {
+                let around_body = Mat4::new_translation(&body_position);
+                let instances: Vec<InstanceData> = belt.iter()
+                    .map(|instance| InstanceData { model_matrix: around_body * instance.model_matrix, ..*instance })
+                    .collect();
+                let coarsest = lod_mesh.level(lod_mesh.levels.len() - 1);
+                framebuffer.set_current_id(None);
+                stats += render_instanced(&mut framebuffer, &uniforms, coarsest, &instances, ASTEROID_SHADER, multisample.as_mut());
+            }
+            Some(stats)
         };
+        if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            match stats {
+                Some(stats) => println!("Render stats: {:?}", stats),
+                None => println!("Render stats are only collected for the mono view"),
+            }
+        }
+
+        if body.shader_index == COMET_SHADER {
+            coma.origin = body_position;
+            coma.radius = lod_mesh.radius * body.scale;
+            if time != coma_time {
+                coma.update(time.saturating_sub(coma_time).max(1) as f32, sun_position - body_position);
+                coma_time = time;
+            }
+            // The coma is part of the comet, so clicking it picks the body.
+            framebuffer.set_current_id(Some(0));
+            coma.render(&mut framebuffer, &(projection_matrix * view_matrix));
+        }
+
+        // Saved before post-processing, while emission is still separate
+        // from the display colors.
+        if window.is_key_pressed(Key::F4, KeyRepeat::No) {
+            match framebuffer.save_hdr("screenshot.hdr") {
+                Ok(()) => println!("Saved screenshot.hdr"),
+                Err(err) => eprintln!("warning: {}", err),
+            }
+        }
 
-        render(&mut framebuffer, &uniforms, &vertex_arrays, shader_index);
+        if settings.show_depth_normals {
+            let proj_inv = (viewport_matrix * projection_matrix)
+                .try_inverse()
+                .unwrap_or(Mat4::identity());
+            framebuffer.buffer = reconstruct_normals_from_depth(&framebuffer, &proj_inv);
+        } else if !settings.anaglyph {
+            if settings.depth_of_field {
+                let focus = projection_matrix * view_matrix * camera.center.push(1.0);
+                depth_of_field(&mut framebuffer, focus.z / focus.w, 0.02, 4);
+            }
+            if settings.motion_blur {
+                motion_blur(&mut framebuffer, 8);
+                framebuffer.accumulate(ACCUMULATION_PERSISTENCE);
+            }
+            settings.post_chain.run(&mut framebuffer);
+        }
+
+        // Six cube faces are too slow to redo every frame, so the panorama is
+        // rendered once per toggle and again only if the render scale changes.
+        if !settings.panorama {
+            panorama = None;
+        } else {
+            if panorama.as_ref().is_none_or(|(width, _)| *width != framebuffer_width) {
+                let pixels = render_panorama(&scene, framebuffer_width, &uniforms, vertex_arrays, body.shader_index);
+                panorama = Some((framebuffer_width, pixels));
+            }
+            if let Some((_, pixels)) = &panorama {
+                let top = (framebuffer_height.saturating_sub(pixels.len() / framebuffer_width)) / 2;
+                framebuffer.buffer.fill(0);
+                framebuffer.buffer[top * framebuffer_width..top * framebuffer_width + pixels.len()]
+                    .copy_from_slice(pixels);
+            }
+        }
 
-        post_process(&mut framebuffer);
+        if settings.screen_grid {
+            draw_screen_grid(&mut framebuffer, SCREEN_GRID_SPACING, SCREEN_GRID_COLOR, SCREEN_GRID_OPACITY);
+        }
 
-        window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
-            .unwrap();
+        // Picking reads the id buffer of the frame just rendered, so a click
+        // selects whatever body is under the cursor on screen.
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if mouse_down && !mouse_was_down {
+            if let Some((mx, my)) = window.get_mouse_pos(MouseMode::Discard) {
+                let fx = (mx * framebuffer_width as f32 / window_width as f32) as usize;
+                let fy = (my * framebuffer_height as f32 / window_height as f32) as usize;
+                if framebuffer.read_id(fx, fy) == Some(0) {
+                    camera.focus(body_position);
+                }
+            }
+        }
+        mouse_was_down = mouse_down;
 
-        std::thread::sleep(frame_delay);
+        if window.is_key_pressed(Key::C, KeyRepeat::No) {
+            match framebuffer.save_png_rgba("screenshot.png") {
+                Ok(()) => println!("Saved screenshot.png"),
+                Err(err) => eprintln!("warning: {}", err),
+            }
+        }
+
+        if window.is_key_pressed(Key::F6, KeyRepeat::No) {
+            let poster = render_tiled(&scene, &stars, (framebuffer_width, framebuffer_height), POSTER_TILES, camera.fov, &uniforms, vertex_arrays, body.shader_index);
+            match poster.save_png_rgba("poster.png") {
+                Ok(()) => println!("Saved poster.png ({}x{})", poster.width, poster.height),
+                Err(err) => eprintln!("warning: {}", err),
+            }
+        }
+
+        if window.is_key_pressed(Key::Insert, KeyRepeat::No) {
+            recorder.toggle();
+        }
+        if !present_frame(&mut window, &framebuffer, &mut recorder, settings.color_order, (window_width, window_height)) {
+            break;
+        }
+
+        previous_model_matrix = Some(model_matrix);
+        previous_view_projection = Some(projection_matrix * view_matrix);
+        quality.update(frame_limiter.elapsed());
+        frame_limiter.end_and_wait();
     }
+
+    recorder.finish();
 }
 
-fn handle_input(window: &Window, camera: &mut Camera, shader_index: &mut usize) {
-    if window.is_key_down(Key::Key1) { *shader_index = 0; }
-    if window.is_key_down(Key::Key2) { *shader_index = 1; }
-    if window.is_key_down(Key::Key3) { *shader_index = 2; }
-    if window.is_key_down(Key::Key4) { *shader_index = 3; }
-    if window.is_key_down(Key::Key5) { *shader_index = 4; }
-    if window.is_key_down(Key::Key6) { *shader_index = 5; }
-    if window.is_key_down(Key::Key7) { *shader_index = 6; }
+// Records the frame if a recording is running, cached frames included so the
+// sequence keeps real time, then shows it at window size. Returns false if the
+// window could not be updated.
+fn present_frame(
+    window: &mut Window,
+    framebuffer: &Framebuffer,
+    recorder: &mut Recorder,
+    color_order: ColorOrder,
+    (width, height): (usize, usize),
+) -> bool {
+    if recorder.is_active() {
+        if let Err(err) = recorder.capture(framebuffer) {
+            eprintln!("warning: {}, stopping recording", err);
+            recorder.finish();
+        }
+    }
+
+    if let Err(err) = window.update_with_buffer(&color_order.present(&framebuffer.upscaled(width, height)), width, height) {
+        eprintln!("error: failed to present frame: {}", err);
+        return false;
+    }
+    true
+}
+
+fn handle_input(window: &Window, camera: &mut Camera, body: &mut Body, settings: &mut Settings) {
+    if window.is_key_down(Key::Key1) { body.shader_index = 0; }
+    if window.is_key_down(Key::Key2) { body.shader_index = 1; }
+    if window.is_key_down(Key::Key3) { body.shader_index = 2; }
+    if window.is_key_down(Key::Key4) { body.shader_index = 3; }
+    if window.is_key_down(Key::Key5) { body.shader_index = 4; }
+    if window.is_key_down(Key::Key6) { body.shader_index = 5; }
+    if window.is_key_down(Key::Key7) { body.shader_index = 6; }
+    if window.is_key_down(Key::Key0) { body.shader_index = ASTEROID_SHADER; }
+    if window.is_key_down(Key::NumPad8) { body.shader_index = URANUS_SHADER; }
+    if window.is_key_down(Key::NumPad9) { body.shader_index = NEPTUNE_SHADER; }
+
+    if window.is_key_pressed(Key::M, KeyRepeat::No) {
+        body.material = (body.material + 1) % MATERIAL_PRESETS.len();
+        println!("Material: {}", MATERIAL_PRESETS[body.material].name);
+    }
+
+    if window.is_key_pressed(Key::N, KeyRepeat::No) {
+        settings.show_depth_normals = !settings.show_depth_normals;
+    }
+    if window.is_key_pressed(Key::F, KeyRepeat::No) {
+        settings.depth_of_field = !settings.depth_of_field;
+    }
+    if window.is_key_pressed(Key::V, KeyRepeat::No) {
+        settings.stereo = !settings.stereo;
+    }
+    if window.is_key_pressed(Key::G, KeyRepeat::No) {
+        settings.anaglyph = !settings.anaglyph;
+    }
+    if window.is_key_pressed(Key::P, KeyRepeat::No) {
+        settings.panorama = !settings.panorama;
+    }
+    let stage_keys = [Key::F8, Key::F9, Key::F10, Key::F11, Key::K, Key::F12];
+    for (key, (name, enabled)) in stage_keys.into_iter().zip(settings.post_chain.stages_mut()) {
+        if window.is_key_pressed(key, KeyRepeat::No) {
+            *enabled = !*enabled;
+            println!("{}: {}", name, if *enabled { "on" } else { "off" });
+        }
+    }
+    if window.is_key_pressed(Key::B, KeyRepeat::No) {
+        settings.comet_tail_blend = settings.comet_tail_blend.next();
+    }
+    if window.is_key_pressed(Key::H, KeyRepeat::No) {
+        settings.shading_frequency = settings.shading_frequency.next();
+        println!("Shading: {:?}", settings.shading_frequency);
+    }
+    if window.is_key_pressed(Key::T, KeyRepeat::No) {
+        settings.texture_filter = settings.texture_filter.next();
+        println!("Texture filter: {:?}", settings.texture_filter);
+    }
+    if window.is_key_pressed(Key::J, KeyRepeat::No) {
+        settings.normal_source = settings.normal_source.next();
+        println!("Normals: {:?}", settings.normal_source);
+    }
+    if window.is_key_pressed(Key::X, KeyRepeat::No) {
+        settings.color_order = settings.color_order.next();
+        println!("Color order: {:?}", settings.color_order);
+    }
+    if window.is_key_pressed(Key::I, KeyRepeat::No) {
+        settings.depth_peel_layers = if settings.depth_peel_layers == 0 { DEPTH_PEEL_LAYERS } else { 0 };
+        println!("Depth peeling layers: {}", settings.depth_peel_layers);
+    }
+    if window.is_key_pressed(Key::F3, KeyRepeat::No) {
+        settings.safe_mode = !settings.safe_mode;
+        println!("Safe mode: {}", if settings.safe_mode { "on" } else { "off" });
+    }
+    if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) || window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+        let step = if window.is_key_down(Key::Equal) { LOD_BIAS_STEP } else { -LOD_BIAS_STEP };
+        settings.lod_bias = (settings.lod_bias + step).clamp(-MAX_LOD_BIAS, MAX_LOD_BIAS);
+        println!("Texture LOD bias: {:+.1}", settings.lod_bias);
+    }
+    if window.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) || window.is_key_pressed(Key::RightBracket, KeyRepeat::Yes) {
+        let step = if window.is_key_down(Key::RightBracket) { 1.0 } else { -1.0 };
+        settings.point_size = (settings.point_size + step).clamp(0.0, MAX_POINT_SIZE);
+        println!("Point size: {}", settings.point_size);
+    }
+    if window.is_key_pressed(Key::Key8, KeyRepeat::Yes) || window.is_key_pressed(Key::Key9, KeyRepeat::Yes) {
+        let step = if window.is_key_down(Key::Key9) { EARTH_ROTATION_STEP } else { -EARTH_ROTATION_STEP };
+        settings.earth_rotation = (settings.earth_rotation + step).rem_euclid(2.0 * PI);
+        println!("Earth rotation: {:.0} deg", settings.earth_rotation.to_degrees());
+    }
+    if window.is_key_pressed(Key::End, KeyRepeat::No) {
+        settings.show_normals = !settings.show_normals;
+        println!("Normal lines: {}", if settings.show_normals { "on" } else { "off" });
+    }
+    if window.is_key_pressed(Key::Backquote, KeyRepeat::No) {
+        let current = RENDER_SCALES.iter().position(|&scale| scale == settings.render_scale).unwrap_or(0);
+        settings.render_scale = RENDER_SCALES[(current + 1) % RENDER_SCALES.len()];
+    }
+    if window.is_key_pressed(Key::Delete, KeyRepeat::No) {
+        settings.coverage_curve = settings.coverage_curve.next();
+        println!("Coverage curve: {:?}", settings.coverage_curve);
+    }
+    if window.is_key_pressed(Key::Apostrophe, KeyRepeat::No) {
+        settings.screen_grid = !settings.screen_grid;
+        println!("Screen grid: {}", if settings.screen_grid { "on" } else { "off" });
+    }
+    if window.is_key_pressed(Key::Home, KeyRepeat::No) {
+        settings.clip_offset = match settings.clip_offset {
+            Some(_) => None,
+            None => Some(0.0),
+        };
+        println!("Clip plane: {}", if settings.clip_offset.is_some() { "on" } else { "off" });
+    }
+    if let Some(offset) = settings.clip_offset.as_mut() {
+        if window.is_key_down(Key::PageUp) {
+            *offset += CLIP_PLANE_SPEED;
+        }
+        if window.is_key_down(Key::PageDown) {
+            *offset -= CLIP_PLANE_SPEED;
+        }
+    }
+    if window.is_key_pressed(Key::Semicolon, KeyRepeat::No) {
+        settings.skybox = !settings.skybox;
+        println!("Skybox: {}", if settings.skybox { "on" } else { "off" });
+    }
+    if window.is_key_pressed(Key::Comma, KeyRepeat::No) {
+        settings.asteroid_belt = !settings.asteroid_belt;
+        println!("Asteroid belt: {}", if settings.asteroid_belt { "on" } else { "off" });
+    }
+    if window.is_key_pressed(Key::F1, KeyRepeat::No) {
+        settings.texture_wrap = settings.texture_wrap.next();
+        println!("Texture wrap: {:?}", settings.texture_wrap);
+    }
+    if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+        let threshold = settings.post_chain.bloom_threshold + BLOOM_THRESHOLD_STEP;
+        settings.post_chain.bloom_threshold = if threshold > 1.0 { 0.0 } else { threshold };
+        println!("Bloom threshold: {:.2}", settings.post_chain.bloom_threshold);
+    }
+    if window.is_key_pressed(Key::F2, KeyRepeat::No) {
+        settings.msaa_samples = if settings.msaa_samples >= 4 { 1 } else { settings.msaa_samples.max(1) * 2 };
+        println!("MSAA: {}x", settings.msaa_samples);
+    }
+    if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+        settings.display_mode = settings.display_mode.next();
+        println!("Display mode: {:?}", settings.display_mode);
+    }
+    if window.is_key_pressed(Key::Backspace, KeyRepeat::No) {
+        settings.cull_backfaces = !settings.cull_backfaces;
+        println!("Backface culling: {}", if settings.cull_backfaces { "on" } else { "off" });
+    }
+    if window.is_key_pressed(Key::Y, KeyRepeat::No) {
+        settings.shadows = !settings.shadows;
+    }
+    if window.is_key_pressed(Key::Z, KeyRepeat::No) {
+        // Cycle the PCF kernel through 1 (hard), 3 and 5 texels.
+        settings.shadow_kernel = if settings.shadow_kernel >= 5 { 1 } else { settings.shadow_kernel + 2 };
+        println!("Shadow kernel: {}x{}", settings.shadow_kernel, settings.shadow_kernel);
+    }
+    if window.is_key_pressed(Key::O, KeyRepeat::No) {
+        settings.auto_orbit = !settings.auto_orbit;
+    }
+    if window.is_key_pressed(Key::U, KeyRepeat::No) {
+        settings.motion_blur = !settings.motion_blur;
+    }
+    if window.is_key_pressed(Key::L, KeyRepeat::No) {
+        settings.vertex_emission_from_color = !settings.vertex_emission_from_color;
+    }
 
     let movement_speed = 1.0;
     let rotation_speed = PI / 50.0;
-    let zoom_speed = 0.1;
+    // Fraction of the viewing distance per frame.
+    let zoom_speed = 0.02;
 
     if window.is_key_down(Key::Left) {
         camera.orbit(rotation_speed, 0.0);
@@ -310,3 +1654,611 @@ fn handle_input(window: &Window, camera: &mut Camera, shader_index: &mut usize)
         camera.zoom(-zoom_speed);
     }
 }
+
+// Uniforms for an untransformed mesh seen from (0, 0, 5) looking at the
+// origin and lit from behind the camera, with every optional pass off, for
+// tests across the crate.
+#[cfg(test)]
+fn test_uniforms<'a>(noise: &'a FastNoiseLite, environment: &'a Texture, width: usize, height: usize) -> Uniforms<'a> {
+    let view_matrix = create_view_matrix(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    let projection_matrix = create_perspective_matrix(45f32.to_radians(), width as f32, height as f32);
+    Uniforms {
+        model_matrix: Mat4::identity(),
+        previous_model_matrix: Mat4::identity(),
+        previous_view_projection: projection_matrix * view_matrix,
+        view_matrix,
+        projection_matrix,
+        viewport_matrix: create_viewport_matrix(width as f32, height as f32),
+        normal_matrix: Mat3::identity(),
+        sun_position: Vec3::new(0.0, 0.0, 10.0),
+        camera_position: Vec3::new(0.0, 0.0, 5.0),
+        time: 0,
+        noise,
+        environment,
+        vertex_emission_from_color: false,
+        comet_tail_blend: BlendMode::Replace,
+        quality: 2,
+        material: &MATERIAL_PRESETS[0],
+        min_triangle_area: 0.0,
+        shading_frequency: ShadingFrequency::PerFragment,
+        texture_filter: Filter::default(),
+        normal_source: NormalSource::Vertex,
+        shadow_map: None,
+        shadow_kernel: 1,
+        display_mode: DisplayMode::Solid,
+        depth_peel_layers: 0,
+        ambient: 0.0,
+        shader_transition: None,
+        msaa_samples: 1,
+        emission: 0.0,
+        lod_bias: 0.0,
+        point_size: 0.0,
+        earth_rotation: 0.0,
+        texture_wrap: Wrap::default(),
+        instance_seed: 0.0,
+        depth_mode: DepthMode::Normal,
+        clip_plane: None,
+        cull_backfaces: false,
+        emissive_maps: &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphere() -> Vec<Vertex> {
+        Obj::load("assets/models/sphere-1.obj").expect("sphere loads").get_vertex_array()
+    }
+
+    #[test]
+    fn emissive_instances_both_glow_and_bloom() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let instances = [-1.2, 1.2].map(|x| InstanceData {
+            model_matrix: create_model_matrix(Vec3::new(x, 0.0, 0.0), 0.4, Vec3::zeros()),
+            seed: 0.0,
+            emission: 1.0,
+        });
+        let mut framebuffer = Framebuffer::new(64, 64);
+        render_instanced(&mut framebuffer, &uniforms, &sphere(), &instances, 2, None);
+
+        // A background pixel right next to each body's silhouette, per half.
+        let halo = |x_range: std::ops::Range<usize>| {
+            let zbuffer = &framebuffer.zbuffer;
+            (0..64).flat_map(|y| x_range.clone().map(move |x| (x, y)))
+                .find(|&(x, y)| zbuffer[y * 64 + x].is_infinite() && x + 2 < 64 && zbuffer[y * 64 + x + 2].is_finite())
+                .map(|(x, y)| y * 64 + x)
+                .expect("a silhouette in this half")
+        };
+        let halos = [halo(0..32), halo(32..64)];
+        for x_range in [0..32, 32..64] {
+            let glowing = (0..64).any(|y| x_range.clone().any(|x| framebuffer.emission_buffer[y * 64 + x] != 0));
+            assert!(glowing, "no emission in columns {:?}", x_range);
+        }
+        for &index in &halos {
+            assert_eq!(framebuffer.buffer[index], 0);
+        }
+
+        PostChain::default().run(&mut framebuffer);
+        for &index in &halos {
+            assert_ne!(framebuffer.buffer[index], 0, "no bloom at pixel {}", index);
+        }
+    }
+
+    #[test]
+    fn frame_cache_renders_once_until_input_or_time_changes() {
+        let mut cache = FrameCache::new();
+        let mut renders = 0;
+        let mut frame = |time: u32, input: bool| {
+            if input {
+                cache.invalidate();
+            }
+            if cache.needs_render(time) {
+                renders += 1;
+                cache.rendered(time);
+            }
+            renders
+        };
+
+        assert_eq!(frame(5, false), 1);
+        assert_eq!(frame(5, false), 1);
+        assert_eq!(frame(5, false), 1);
+        assert_eq!(frame(5, true), 2);
+        assert_eq!(frame(5, false), 2);
+        assert_eq!(frame(6, false), 3);
+    }
+
+    #[test]
+    fn scissored_full_screen_triangle_stays_inside_the_quadrant() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let vertices = [Vec3::new(-10.0, -10.0, 0.0), Vec3::new(10.0, -10.0, 0.0), Vec3::new(0.0, 10.0, 0.0)]
+            .map(|position| Vertex::new(position, normal, Vec2::zeros()));
+
+        for msaa_samples in [1, 4] {
+            let uniforms = Uniforms { msaa_samples, ..test_uniforms(&noise, &environment, 32, 32) };
+            let mut framebuffer = Framebuffer::new(32, 32);
+            framebuffer.set_scissor(Some((0, 0, 16, 16)));
+            render(&mut framebuffer, &uniforms, &vertices, 2);
+
+            for y in 0..32 {
+                for x in 0..32 {
+                    let index = y * 32 + x;
+                    let inside = x < 16 && y < 16;
+                    assert_eq!(framebuffer.zbuffer[index].is_finite(), inside, "{}x MSAA, depth at ({}, {})", msaa_samples, x, y);
+                    if !inside {
+                        assert_eq!(framebuffer.buffer[index], 0, "{}x MSAA, color at ({}, {})", msaa_samples, x, y);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backface_culling_keeps_the_front_surface_and_rasterizes_fewer_triangles() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let vertices = sphere();
+
+        let mut framebuffer = Framebuffer::new(64, 64);
+        let all = render(&mut framebuffer, &uniforms, &vertices, 2);
+        let center_depth = framebuffer.zbuffer[32 * 64 + 32];
+        assert!(all.fragments_written > 0);
+        assert!(all.fragments_written <= all.fragments_generated);
+
+        let mut framebuffer = Framebuffer::new(64, 64);
+        let culled = render(&mut framebuffer, &Uniforms { cull_backfaces: true, ..uniforms }, &vertices, 2);
+        assert!(culled.fragments_written <= culled.fragments_generated);
+        assert!(culled.triangles_submitted < all.triangles_submitted);
+        assert!(culled.triangles_culled > all.triangles_culled);
+        assert!(culled.fragments_generated < all.fragments_generated);
+        // Only the far side went away; the visible surface is unchanged.
+        assert_eq!(framebuffer.zbuffer[32 * 64 + 32], center_depth);
+    }
+
+    #[test]
+    fn tile_frusta_share_seams_and_cover_the_full_view() {
+        let (fov, aspect_ratio, columns, rows) = (45f32.to_radians(), 1.6, 3, 2);
+        let (full_left, full_right, full_bottom, full_top) = tile_frustum(fov, aspect_ratio, 0, 0, 1, 1);
+        for row in 0..rows {
+            for column in 0..columns {
+                let (left, right, bottom, top) = tile_frustum(fov, aspect_ratio, column, row, columns, rows);
+                if column + 1 < columns {
+                    assert_eq!(right, tile_frustum(fov, aspect_ratio, column + 1, row, columns, rows).0);
+                }
+                if row + 1 < rows {
+                    assert_eq!(bottom, tile_frustum(fov, aspect_ratio, column, row + 1, columns, rows).3);
+                }
+                if column == 0 {
+                    assert_eq!(left, full_left);
+                }
+                if column == columns - 1 {
+                    assert_eq!(right, full_right);
+                }
+                if row == 0 {
+                    assert_eq!(top, full_top);
+                }
+                if row == rows - 1 {
+                    assert_eq!(bottom, full_bottom);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn half_render_scale_renders_a_quarter_of_the_pixels_and_presents_at_window_size() {
+        let (window_width, window_height) = (800, 600);
+        let framebuffer = Framebuffer::new(render_size(window_width, 0.5), render_size(window_height, 0.5));
+        assert_eq!(framebuffer.buffer.len() * 4, window_width * window_height);
+        assert_eq!(framebuffer.upscaled(window_width, window_height).len(), window_width * window_height);
+    }
+
+    fn facing_triangle(z: f32, size: f32) -> Vec<Vertex> {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        [Vec3::new(-size, -size, z), Vec3::new(size, -size, z), Vec3::new(0.0, size, z)]
+            .map(|position| Vertex::new(position, normal, Vec2::zeros()))
+            .to_vec()
+    }
+
+    #[test]
+    fn triangle_nearer_the_camera_wins_in_either_draw_order() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 32, 32);
+        // The camera sits at z = 5, so z = 1 is the nearer of the two.
+        let (near, far) = (facing_triangle(1.0, 1.0), facing_triangle(-1.0, 1.0));
+
+        for order in [[(&near, 1), (&far, 0)], [(&far, 0), (&near, 1)]] {
+            let mut framebuffer = Framebuffer::new(32, 32);
+            for (vertices, id) in order {
+                framebuffer.set_current_id(Some(id));
+                render(&mut framebuffer, &uniforms, vertices, 2);
+            }
+            assert_eq!(framebuffer.read_id(16, 16), Some(1));
+        }
+    }
+
+    #[test]
+    fn vertex_on_the_camera_plane_leaves_no_nan_or_infinity() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 32, 32);
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        // z = 5 is the camera's own plane, where clip w is exactly 0.
+        let vertices: Vec<Vertex> = [Vec3::new(1.0, 0.0, 5.0), Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0)]
+            .map(|position| Vertex::new(position, normal, Vec2::zeros()))
+            .to_vec();
+        assert_eq!(vertex_shader(&vertices[0], &uniforms).clip_position.w, 0.0);
+
+        let mut framebuffer = Framebuffer::new(32, 32);
+        let stats = render(&mut framebuffer, &uniforms, &vertices, 2);
+        assert_eq!(stats.triangles_culled, 1);
+        assert!(framebuffer.zbuffer.iter().all(|&depth| depth.is_finite() || depth == f32::INFINITY));
+        assert!(framebuffer.velocity_buffer.iter().all(|velocity| velocity.iter().all(|c| c.is_finite())));
+    }
+
+    #[test]
+    fn a_camera_cut_resets_accumulation_but_small_moves_do_not() {
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let previous = (camera.eye, camera.center);
+        camera.eye.x += 0.5;
+        assert!(!camera_jumped(previous, &camera));
+        camera.center.y += 0.5;
+        assert!(!camera_jumped(previous, &camera));
+        camera.eye = Vec3::new(0.0, 0.0, 20.0);
+        assert!(camera_jumped(previous, &camera));
+        camera.eye = previous.0;
+        camera.center = Vec3::new(3.0, 0.0, 0.0);
+        assert!(camera_jumped(previous, &camera));
+
+        // A white frame, then black frames, with the main loop's reset rule.
+        let mut framebuffer = Framebuffer::new(1, 1);
+        let mut frame = |camera: &Camera, color: u32| {
+            if camera_jumped(previous, camera) {
+                framebuffer.clear_accum();
+            }
+            framebuffer.buffer[0] = color;
+            framebuffer.accumulate(ACCUMULATION_PERSISTENCE);
+            framebuffer.buffer[0]
+        };
+        let still = Camera::new(previous.0, previous.1, Vec3::new(0.0, 1.0, 0.0));
+        let nudged = Camera::new(previous.0 + Vec3::new(0.5, 0.0, 0.0), previous.1, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(frame(&still, 0xFFFFFF), 0xFFFFFF);
+        assert_ne!(frame(&nudged, 0x000000), 0x000000);
+        assert_eq!(frame(&camera, 0x000000), 0x000000);
+    }
+
+    #[test]
+    fn read_id_names_the_body_under_each_pixel() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let vertices = sphere();
+        let mut framebuffer = Framebuffer::new(64, 64);
+        // World x = -1.2 and 1.2 land near pixel columns 13 and 50.
+        for (index, x) in [(0, -1.2), (1, 1.2)] {
+            let model_matrix = create_model_matrix(Vec3::new(x, 0.0, 0.0), 0.4, Vec3::zeros());
+            framebuffer.set_current_id(Some(index));
+            render(&mut framebuffer, &Uniforms { model_matrix, ..uniforms }, &vertices, 2);
+        }
+        framebuffer.set_current_id(None);
+
+        assert_eq!(framebuffer.read_id(13, 32), Some(0));
+        assert_eq!(framebuffer.read_id(50, 32), Some(1));
+        assert_eq!(framebuffer.read_id(32, 32), None);
+        assert_eq!(framebuffer.read_id(64, 32), None);
+    }
+
+    #[test]
+    fn foreground_beyond_the_skybox_radius_still_draws_over_it() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 32, 32);
+        let skybox_matrix = create_model_matrix(uniforms.camera_position, SKYBOX_SCALE, Vec3::zeros());
+        let skybox_uniforms = Uniforms { model_matrix: skybox_matrix, depth_mode: DepthMode::AlwaysFar, ..uniforms };
+
+        // The far triangle sits twice as far out as the skybox shell.
+        for z in [1.0, -2.0 * SKYBOX_SCALE] {
+            let mut framebuffer = Framebuffer::new(32, 32);
+            framebuffer.set_current_id(Some(0));
+            render(&mut framebuffer, &skybox_uniforms, &sphere(), SKYBOX_SHADER);
+            assert_eq!(framebuffer.read_id(16, 16), Some(0));
+            assert_eq!(framebuffer.zbuffer[16 * 32 + 16], FAR_DEPTH);
+
+            framebuffer.set_current_id(Some(1));
+            render(&mut framebuffer, &uniforms, &facing_triangle(z, (5.0 - z) * 0.2), 2);
+            assert_eq!(framebuffer.read_id(16, 16), Some(1), "triangle at z = {}", z);
+        }
+    }
+
+    #[test]
+    fn clip_plane_discards_only_the_negative_side() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let vertices = sphere();
+        let mut whole = Framebuffer::new(64, 64);
+        render(&mut whole, &uniforms, &vertices, 2);
+        let mut cut = Framebuffer::new(64, 64);
+        // Keeps world x >= 0; the camera looks down the plane from z = 5.
+        let clip_plane = Some(Vec4::new(1.0, 0.0, 0.0, 0.0));
+        render(&mut cut, &Uniforms { clip_plane, ..uniforms }, &vertices, 2);
+
+        for y in 0..64 {
+            for x in 0..64 {
+                let index = y * 64 + x;
+                if x < 31 {
+                    assert!(cut.zbuffer[index].is_infinite(), "kept ({}, {})", x, y);
+                } else if x > 32 {
+                    assert_eq!(cut.zbuffer[index], whole.zbuffer[index], "changed ({}, {})", x, y);
+                }
+            }
+        }
+        assert!((0..64 * 64).any(|i| i % 64 < 31 && whole.zbuffer[i].is_finite()));
+    }
+
+    #[test]
+    fn vertex_color_emission_fades_away_from_the_bright_vertex() {
+        // A white environment makes the skybox shader a flat white surface.
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0xFFFFFF]));
+        let uniforms = Uniforms { vertex_emission_from_color: true, ..test_uniforms(&noise, &environment, 64, 64) };
+        let mut vertices = facing_triangle(0.0, 1.0);
+        vertices[0].color = Color::black();
+        vertices[1].color = Color::black();
+        vertices[2].color = Color::new(255, 255, 255);
+        let mut framebuffer = Framebuffer::new(64, 64);
+        render(&mut framebuffer, &uniforms, &vertices, SKYBOX_SHADER);
+
+        // Down the center column, from just below the top vertex to the
+        // bottom edge.
+        let glow: Vec<u32> = [20, 28, 36, 44].iter().map(|&y| framebuffer.emission_buffer[y * 64 + 32] & 0xFF).collect();
+        assert!(glow[0] > 200, "{:?}", glow);
+        assert!(glow.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", glow);
+        assert!(glow[3] < 40, "{:?}", glow);
+
+        let mut off = Framebuffer::new(64, 64);
+        render(&mut off, &Uniforms { vertex_emission_from_color: false, ..uniforms }, &vertices, SKYBOX_SHADER);
+        assert!(off.emission_buffer.iter().all(|&emission| emission == 0));
+    }
+
+    #[test]
+    fn geometric_normals_light_a_face_whose_vertex_normals_are_garbage() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 32, 32);
+        let draw = |vertices: &[Vertex], normal_source: NormalSource| {
+            let mut framebuffer = Framebuffer::new(32, 32);
+            render(&mut framebuffer, &Uniforms { normal_source, ..uniforms }, vertices, 2);
+            framebuffer.buffer
+        };
+        // Counter-clockwise toward the camera and the sun, both on +z.
+        let correct = facing_triangle(0.0, 1.0);
+        let mut garbage = correct.clone();
+        for (vertex, normal) in garbage.iter_mut().zip([Vec3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)]) {
+            vertex.normal = normal;
+        }
+
+        let reference = draw(&correct, NormalSource::Vertex);
+        assert!(reference[16 * 32 + 16] != 0);
+        assert_eq!(draw(&garbage, NormalSource::Geometric), reference);
+        assert_ne!(draw(&garbage, NormalSource::Vertex), reference);
+    }
+
+    #[test]
+    fn per_vertex_shading_runs_the_shader_three_times_per_triangle() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let vertices = sphere();
+
+        let mut framebuffer = Framebuffer::new(64, 64);
+        let per_vertex = render(&mut framebuffer, &Uniforms { shading_frequency: ShadingFrequency::PerVertex, ..uniforms }, &vertices, 2);
+        assert_eq!(per_vertex.shader_invocations, vertices.len());
+        assert_eq!(per_vertex.shader_invocations % 3, 0);
+
+        let mut framebuffer = Framebuffer::new(64, 64);
+        let per_fragment = render(&mut framebuffer, &uniforms, &vertices, 2);
+        assert_eq!(per_fragment.shader_invocations, per_fragment.fragments_generated);
+        assert_ne!(per_fragment.shader_invocations, per_vertex.shader_invocations);
+    }
+
+    #[test]
+    fn point_mode_lights_about_one_pixel_per_vertex() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 256, 256);
+        let vertices = sphere();
+        let unique: std::collections::HashSet<[u32; 3]> = vertices.iter()
+            .map(|vertex| [vertex.position.x.to_bits(), vertex.position.y.to_bits(), vertex.position.z.to_bits()])
+            .collect();
+        let covered = |display_mode: DisplayMode| {
+            let mut framebuffer = Framebuffer::new(256, 256);
+            render(&mut framebuffer, &Uniforms { display_mode, ..uniforms }, &vertices, 2);
+            framebuffer.zbuffer.iter().filter(|depth| depth.is_finite()).count()
+        };
+
+        let points = covered(DisplayMode::Point);
+        // Front and back vertices can land on the same pixel, never more.
+        assert!(points <= unique.len(), "{} pixels for {} vertices", points, unique.len());
+        assert!(points * 2 > unique.len(), "{} pixels for {} vertices", points, unique.len());
+        let solid = covered(DisplayMode::Solid);
+        assert!(points * 5 < solid, "{} point pixels against {} solid", points, solid);
+    }
+
+    #[test]
+    fn larger_point_size_covers_more_pixels_per_vertex() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = Uniforms { display_mode: DisplayMode::Point, ..test_uniforms(&noise, &environment, 64, 64) };
+        let vertices = facing_triangle(0.0, 1.0);
+        let covered = |point_size: f32| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            render(&mut framebuffer, &Uniforms { point_size, ..uniforms }, &vertices, 2);
+            framebuffer.alpha_buffer.iter().filter(|&&alpha| alpha > 0).count()
+        };
+
+        assert_eq!(covered(0.0), 3);
+        assert_eq!(covered(0.5), 3);
+        let (small, large) = (covered(3.0), covered(8.0));
+        assert!(small > 3, "{}", small);
+        assert!(large > small, "{} vs {}", large, small);
+    }
+
+    #[test]
+    fn normal_lines_start_at_each_vertex_and_follow_its_normal() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let mut vertices = facing_triangle(0.0, 1.0);
+        for vertex in &mut vertices {
+            vertex.normal = Vec3::new(1.0, 0.0, 0.0);
+        }
+        let mut framebuffer = Framebuffer::new(64, 64);
+        // Half a unit at this distance is a little under 8 pixels.
+        render_normals(&mut framebuffer, &uniforms, &vertices, 0.5);
+
+        let drawn = |x: f32, y: f32| framebuffer.buffer[y as usize * 64 + x as usize] == NORMAL_LINE_COLOR;
+        for vertex in &vertices {
+            let start = vertex_shader(vertex, &uniforms).transformed_position;
+            assert!(drawn(start.x, start.y), "no line at {:?}", start);
+            assert!(drawn(start.x + 6.0, start.y));
+            assert!(!drawn(start.x + 9.0, start.y));
+            assert!(!drawn(start.x - 2.0, start.y));
+            assert!(!drawn(start.x, start.y - 2.0) && !drawn(start.x, start.y + 2.0));
+        }
+        let pixels = framebuffer.buffer.iter().filter(|&&pixel| pixel == NORMAL_LINE_COLOR).count();
+        assert!((3 * 7..=3 * 9).contains(&pixels), "{}", pixels);
+    }
+
+    #[test]
+    fn lower_min_area_threshold_keeps_more_triangles() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let vertices = sphere();
+        let draw = |min_triangle_area: f32| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            render(&mut framebuffer, &Uniforms { min_triangle_area, ..uniforms }, &vertices, 2)
+        };
+
+        let (all, some, few) = (draw(0.0), draw(0.5), draw(2.0));
+        assert!(all.triangles_submitted > some.triangles_submitted, "{} vs {}", all.triangles_submitted, some.triangles_submitted);
+        assert!(some.triangles_submitted > few.triangles_submitted, "{} vs {}", some.triangles_submitted, few.triangles_submitted);
+        assert!(all.fragments_generated > some.fragments_generated);
+        assert!(some.fragments_generated > few.fragments_generated);
+    }
+
+    #[test]
+    fn planet_in_front_of_the_sun_hides_its_corona() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let sun = test_uniforms(&noise, &environment, 64, 64);
+        // Halfway between the sun at the origin and the camera at z = 5,
+        // over the sun's limb and the inner corona.
+        let planet = Uniforms { model_matrix: create_model_matrix(Vec3::new(0.35, 0.0, 2.5), 0.4, Vec3::zeros()), ..sun };
+        let vertices = sphere();
+        let draw = |passes: &[(&Uniforms, usize)]| {
+            let mut framebuffer = Framebuffer::new(64, 64);
+            for &(uniforms, shader_index) in passes {
+                framebuffer.set_current_id(Some(shader_index));
+                render(&mut framebuffer, uniforms, &vertices, shader_index);
+            }
+            framebuffer
+        };
+
+        let sun_alone = draw(&[(&sun, 0)]);
+        let planet_alone = draw(&[(&planet, 2)]);
+        let covered: Vec<usize> = (0..64 * 64).filter(|&i| planet_alone.id_buffer[i] != 0).collect();
+        assert!(!covered.is_empty());
+        let glowing = covered.iter().filter(|&&i| sun_alone.emission_buffer[i] != 0).count();
+        assert!(glowing * 2 > covered.len(), "{} of {} pixels glow without the planet", glowing, covered.len());
+        assert!(covered.iter().any(|&i| sun_alone.zbuffer[i].is_infinite() && sun_alone.emission_buffer[i] != 0));
+
+        for passes in [[(&planet, 2), (&sun, 0)], [(&sun, 0), (&planet, 2)]] {
+            let both = draw(&passes);
+            for &i in &covered {
+                assert_eq!(both.emission_buffer[i], planet_alone.emission_buffer[i], "corona over pixel {}", i);
+            }
+        }
+    }
+
+    #[test]
+    fn transition_midpoint_averages_the_two_shaders() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let vertex = Vertex::new(Vec3::new(0.0, 0.6, 0.8), Vec3::new(0.0, 0.6, 0.8), Vec2::zeros());
+        let fragment = vertex_fragment(&vertex, &uniforms.sun_position);
+
+        let mut transition = ShaderTransition::new(2);
+        transition.retarget(4, 100);
+        let midpoint = 100 + (SHADER_TRANSITION_FRAMES / 2.0) as u32;
+        assert_eq!(transition.blend(midpoint), Some((2, 0.5)));
+        assert_eq!(transition.blend(100 + SHADER_TRANSITION_FRAMES as u32), None);
+
+        let blended = shade_with_transition(&fragment, &Uniforms { shader_transition: transition.blend(midpoint), ..uniforms }, 4);
+        let (from, to) = (shade_fragment(&fragment, &uniforms, 2).color, shade_fragment(&fragment, &uniforms, 4).color);
+        assert_ne!(from.to_hex(), to.to_hex());
+        let channels = |color: Color| [16, 8, 0].map(|shift| ((color.to_hex() >> shift) & 0xFF) as i32);
+        for ((mixed, a), b) in channels(blended.color).into_iter().zip(channels(from)).zip(channels(to)) {
+            assert!((mixed - (a + b) / 2).abs() <= 1, "{} is not halfway between {} and {}", mixed, a, b);
+        }
+    }
+
+    #[test]
+    fn moving_the_sun_moves_the_lit_side_and_the_comet_tail() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 64, 64);
+        let vertices = sphere();
+        // Summed brightness of a buffer's left and right halves.
+        let halves = |buffer: &[u32]| {
+            let mut sums = [0u32; 2];
+            for (index, &pixel) in buffer.iter().enumerate() {
+                sums[usize::from(index % 64 >= 32)] += ((pixel >> 16) & 0xFF) + ((pixel >> 8) & 0xFF) + (pixel & 0xFF);
+            }
+            sums
+        };
+
+        for sun_x in [10.0, -10.0] {
+            let sun = Uniforms { sun_position: Vec3::new(sun_x, 0.0, 0.0), ..uniforms };
+            let mut planet = Framebuffer::new(64, 64);
+            render(&mut planet, &sun, &vertices, 2);
+            let [left, right] = halves(&planet.buffer);
+            assert_eq!(right > left, sun_x > 0.0, "lit side with the sun at x = {}: {} / {}", sun_x, left, right);
+
+            let mut comet = Framebuffer::new(64, 64);
+            render(&mut comet, &sun, &vertices, 6);
+            let [left, right] = halves(&comet.emission_buffer);
+            assert_eq!(left > right, sun_x > 0.0, "tail with the sun at x = {}: {} / {}", sun_x, left, right);
+        }
+    }
+
+    #[test]
+    fn spinning_body_turns_its_surface_around_a_fixed_tilted_axis() {
+        let body = Body::new(1, Vec3::zeros(), 1.0).with_spin(0.4, 0.1);
+        let matrices = [0, 10].map(|time| create_model_matrix(body.translation, body.scale, body.rotation(time)));
+        let transform = |matrix: &Mat4, point: Vec3| (matrix * point.push(1.0)).xyz();
+
+        let surface = matrices.map(|matrix| transform(&matrix, Vec3::new(1.0, 0.0, 0.0)));
+        assert!((surface[0] - surface[1]).magnitude() > 0.5, "{:?}", surface);
+
+        let tilted_axis = Vec3::new(-0.4f32.sin(), 0.4f32.cos(), 0.0);
+        for matrix in &matrices {
+            assert!((transform(matrix, Vec3::new(0.0, 1.0, 0.0)) - tilted_axis).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn seeking_renders_the_same_frame_as_ticking_there() {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = test_uniforms(&noise, &environment, 32, 32);
+        let vertices = sphere();
+        let frame_hash = |time: u32| {
+            let mut framebuffer = Framebuffer::new(32, 32);
+            render(&mut framebuffer, &Uniforms { time, ..uniforms }, &vertices, 3);
+            let mut hasher = DefaultHasher::new();
+            framebuffer.buffer.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let mut ticked = TimeControl::new();
+        for _ in 0..40 {
+            ticked.tick();
+        }
+        let mut seeked = TimeControl::new();
+        seeked.seek(40);
+        seeked.tick();
+        assert_eq!((seeked.time(), ticked.time()), (40, 40));
+        assert_eq!(frame_hash(seeked.time()), frame_hash(ticked.time()));
+        assert_ne!(frame_hash(40), frame_hash(0));
+    }
+}
@@ -0,0 +1,115 @@
+use fastnoise_lite::FastNoiseLite;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::vertex::Vertex;
+
+// A look applied on top of whichever planet shader is active: `noise_scale`
+// stretches the shader's procedural pattern, `tint` recolors the result, and
+// `roughness` adds fine-grained brightness variation.
+pub struct MaterialPreset {
+  pub name: &'static str,
+  pub tint: Color,
+  pub tint_strength: f32,
+  pub roughness: f32,
+  pub noise_scale: f32,
+}
+
+pub const MATERIAL_PRESETS: [MaterialPreset; 4] = [
+  MaterialPreset {
+    name: "default",
+    tint: Color::new(255, 255, 255),
+    tint_strength: 0.0,
+    roughness: 0.0,
+    noise_scale: 1.0,
+  },
+  MaterialPreset {
+    name: "icy",
+    tint: Color::new(210, 235, 255),
+    tint_strength: 0.6,
+    roughness: 0.1,
+    noise_scale: 0.7,
+  },
+  MaterialPreset {
+    name: "rocky",
+    tint: Color::new(120, 105, 90),
+    tint_strength: 0.4,
+    roughness: 0.8,
+    noise_scale: 1.6,
+  },
+  MaterialPreset {
+    name: "gaseous",
+    tint: Color::new(230, 200, 150),
+    tint_strength: 0.3,
+    roughness: 0.0,
+    noise_scale: 0.4,
+  },
+];
+
+// Gives a mesh scattered smooth, icy patches on an otherwise matte surface,
+// so specular response varies across a single body.
+pub fn paint_roughness(vertices: &mut [Vertex], noise: &FastNoiseLite) {
+  for vertex in vertices {
+    let patch = noise.get_noise_3d(
+      vertex.position.x * 150.0,
+      vertex.position.y * 150.0,
+      vertex.position.z * 150.0,
+    );
+    vertex.roughness = if patch > 0.35 { 0.2 } else { 1.0 };
+  }
+}
+
+impl MaterialPreset {
+  pub fn prepare(&self, fragment: &mut Fragment) {
+    fragment.noise_position = fragment.vertex_position * self.noise_scale;
+  }
+
+  pub fn apply(&self, color: Color, fragment: &Fragment, noise: &FastNoiseLite) -> Color {
+    let tinted = color.lerp(&(self.tint * color.luminance().max(0.2)), self.tint_strength);
+    if self.roughness <= 0.0 {
+      return tinted;
+    }
+
+    let grain = noise.get_noise_3d(
+      fragment.noise_position.x * 200.0,
+      fragment.noise_position.y * 200.0,
+      fragment.noise_position.z * 200.0,
+    );
+    tinted * (1.0 + grain * 0.3 * self.roughness)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use nalgebra_glm::{Vec2, Vec3};
+  use crate::create_noise;
+  use crate::shaders::mars_shader;
+  use crate::texture::Texture;
+  use crate::triangle::vertex_fragment;
+
+  #[test]
+  fn icy_preset_shifts_mars_toward_blue_white() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    let channels = |color: Color| [16, 8, 0].map(|shift| ((color.to_hex() >> shift) & 0xFF) as i32);
+    let shade = |preset: &MaterialPreset, position: Vec3| {
+      let vertex = Vertex::new(position, position, Vec2::zeros());
+      let mut fragment = vertex_fragment(&vertex, &Vec3::new(0.0, 0.0, 10.0));
+      fragment.intensity = 1.0;
+      preset.prepare(&mut fragment);
+      channels(preset.apply(mars_shader(&fragment, &uniforms).color, &fragment, &noise))
+    };
+
+    let (default, icy) = (&MATERIAL_PRESETS[0], &MATERIAL_PRESETS[1]);
+    assert_eq!(icy.name, "icy");
+    for position in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.6, 0.0, 0.8), Vec3::new(0.0, -0.8, 0.6)] {
+      let [red, green, blue] = shade(default, position);
+      let [icy_red, icy_green, icy_blue] = shade(icy, position);
+      // Mars is red-dominant; under ice blue catches up with red and the
+      // weakest channel rises, so the color is both bluer and whiter.
+      assert!(blue < red);
+      assert!(icy_blue - icy_red > blue - red, "{position:?}");
+      assert!(icy_red.min(icy_green).min(icy_blue) > red.min(green).min(blue), "{position:?}");
+    }
+  }
+}
@@ -0,0 +1,182 @@
+use nalgebra_glm::Vec2;
+use crate::framebuffer::Framebuffer;
+use crate::triangle::SampleCoverage;
+
+const SINGLE_SAMPLE: [(f32, f32); 1] = [(0.5, 0.5)];
+const TWO_SAMPLES: [(f32, f32); 2] = [(0.25, 0.25), (0.75, 0.75)];
+// Rotated grid, so near-horizontal and near-vertical edges each get four
+// distinct coverage steps.
+const FOUR_SAMPLES: [(f32, f32); 4] = [(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)];
+
+// Subsample positions within a pixel for a sample count; unsupported counts
+// fall back to the next lower supported pattern.
+pub fn sample_offsets(samples: usize) -> &'static [(f32, f32)] {
+    match samples {
+        0 | 1 => &SINGLE_SAMPLE,
+        2 | 3 => &TWO_SAMPLES,
+        _ => &FOUR_SAMPLES,
+    }
+}
+
+// Per-sample color, emission, velocity and depth for one multisampled pass.
+// Depth starts out as the framebuffer's own depth, so geometry already drawn
+// still occludes. Samples no triangle reaches resolve to whatever the
+// framebuffer held underneath. `reset` reuses the allocation for the next
+// pass.
+pub struct MultisampleBuffer {
+    width: usize,
+    height: usize,
+    samples: usize,
+    depth: Vec<f32>,
+    color: Vec<u32>,
+    emission: Vec<u32>,
+    velocity: Vec<Vec2>,
+    written: Vec<bool>,
+}
+
+impl MultisampleBuffer {
+    pub fn new(framebuffer: &Framebuffer, samples: usize) -> Self {
+        let samples = sample_offsets(samples).len();
+        let size = framebuffer.width * framebuffer.height * samples;
+        MultisampleBuffer {
+            width: framebuffer.width,
+            height: framebuffer.height,
+            samples,
+            depth: framebuffer.zbuffer.iter().flat_map(|&depth| std::iter::repeat_n(depth, samples)).collect(),
+            color: vec![0; size],
+            emission: vec![0; size],
+            velocity: vec![Vec2::zeros(); size],
+            written: vec![false; size],
+        }
+    }
+
+    // Starts a new pass over `framebuffer`, reallocating only if its size
+    // changed.
+    pub fn reset(&mut self, framebuffer: &Framebuffer) {
+        if (self.width, self.height) != (framebuffer.width, framebuffer.height) {
+            *self = MultisampleBuffer::new(framebuffer, self.samples);
+            return;
+        }
+        for (samples, &depth) in self.depth.chunks_exact_mut(self.samples).zip(&framebuffer.zbuffer) {
+            samples.fill(depth);
+        }
+        self.written.fill(false);
+    }
+
+    // Writes one shaded color to every covered sample that passes its own
+    // depth test.
+    // Returns whether any sample passed the depth test.
+    pub fn write(&mut self, x: usize, y: usize, coverage: &SampleCoverage, color: u32, emission: u32, velocity: Vec2) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let base = (y * self.width + x) * self.samples;
+        let mut any = false;
+        for i in 0..self.samples {
+            if coverage.mask & (1 << i) == 0 || coverage.depths[i] >= self.depth[base + i] {
+                continue;
+            }
+            self.depth[base + i] = coverage.depths[i];
+            self.color[base + i] = color;
+            self.emission[base + i] = emission;
+            self.velocity[base + i] = velocity;
+            self.written[base + i] = true;
+            any = true;
+        }
+        any
+    }
+
+    // Nearest depth among a pixel's samples. Depth is never averaged: at a
+    // silhouette the mean of foreground and background depths lies in empty
+    // space, and fog or depth of field keyed on it would halo the edge.
+    // Unwritten samples still hold the framebuffer depth they started from.
+    fn resolve_depth(&self, base: usize) -> f32 {
+        self.depth[base..base + self.samples].iter().copied().fold(f32::INFINITY, f32::min)
+    }
+
+    // Velocity of the nearest written sample, so motion blur follows the
+    // same foreground surface the resolved depth belongs to.
+    fn resolve_velocity(&self, base: usize) -> Vec2 {
+        (base..base + self.samples)
+            .filter(|&i| self.written[i])
+            .min_by(|&a, &b| self.depth[a].total_cmp(&self.depth[b]))
+            .map_or(Vec2::zeros(), |i| self.velocity[i])
+    }
+
+    // Averages each pixel's color and emission samples into the framebuffer,
+    // with unwritten samples standing in for the existing framebuffer
+    // contents, and resolves depth with `resolve_depth`.
+    pub fn resolve(&self, framebuffer: &mut Framebuffer) {
+        for index in 0..self.width * self.height {
+            let base = index * self.samples;
+            let written = self.written[base..base + self.samples].iter().filter(|&&w| w).count();
+            if written == 0 {
+                continue;
+            }
+
+            let mut color = [0u32; 3];
+            let mut emission = [0u32; 3];
+            for i in base..base + self.samples {
+                let (c, e) = if self.written[i] {
+                    (self.color[i], self.emission[i])
+                } else {
+                    (framebuffer.buffer[index], framebuffer.emission_buffer[index])
+                };
+                for (channel, shift) in [16, 8, 0].into_iter().enumerate() {
+                    color[channel] += (c >> shift) & 0xFF;
+                    emission[channel] += (e >> shift) & 0xFF;
+                }
+            }
+
+            let n = self.samples as u32;
+            let pack = |c: [u32; 3]| ((c[0] / n) << 16) | ((c[1] / n) << 8) | (c[2] / n);
+            let coverage = written as f32 / self.samples as f32;
+            framebuffer.write_resolved(
+                index,
+                pack(color),
+                pack(emission),
+                self.resolve_depth(base),
+                self.resolve_velocity(base),
+                coverage,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::triangle::MAX_SAMPLES;
+
+    #[test]
+    fn half_covered_edge_pixel_resolves_to_the_blend_of_both_colors() {
+        let mut framebuffer = Framebuffer::new(2, 2);
+        framebuffer.set_background_color(0x000000);
+        framebuffer.clear();
+        let mut multisample = MultisampleBuffer::new(&framebuffer, 4);
+        let coverage = SampleCoverage { mask: 0b0011, depths: [0.5; MAX_SAMPLES] };
+        assert!(multisample.write(1, 0, &coverage, 0xFFFFFF, 0, Vec2::zeros()));
+        multisample.resolve(&mut framebuffer);
+
+        assert_eq!(framebuffer.buffer[1], 0x7F7F7F);
+        assert_eq!(framebuffer.alpha_buffer[1], 128);
+        assert_eq!(framebuffer.zbuffer[1], 0.5);
+        // Pixels no sample reached keep the background.
+        assert_eq!(framebuffer.buffer[0], 0x000000);
+        assert_eq!(framebuffer.zbuffer[0], f32::INFINITY);
+    }
+
+    #[test]
+    fn edge_pixel_depth_is_the_nearest_sample_not_the_average() {
+        let mut framebuffer = Framebuffer::new(1, 1);
+        framebuffer.point_with_emission(0, 0, 0.8, 0);
+        let mut multisample = MultisampleBuffer::new(&framebuffer, 4);
+        let mut depths = [f32::INFINITY; MAX_SAMPLES];
+        depths[0] = 0.2;
+        depths[1] = 0.4;
+        multisample.write(0, 0, &SampleCoverage { mask: 0b0011, depths }, 0xFFFFFF, 0, Vec2::zeros());
+        multisample.resolve(&mut framebuffer);
+
+        assert_eq!(framebuffer.zbuffer[0], 0.2);
+    }
+}
@@ -1,62 +1,592 @@
-use tobj;
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
+use crate::color::Color;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::rng::Rng;
+use crate::texture::Texture;
+use std::path::Path;
+
+// Magenta checkerboard for an emissive map that fails to load.
+const MISSING_EMISSIVE_COLOR: u32 = 0xFF00FF;
 
 pub struct Obj {
     meshes: Vec<Mesh>,
+    materials: Vec<Material>,
+}
+
+// The parts of an MTL material the renderer uses. `emissive_map` comes from
+// `map_Ke`, which tobj does not parse itself.
+pub struct Material {
+    pub emissive_map: Option<Texture>,
+}
+
+// Summary of a loaded model for diagnosing import problems. Positions are
+// counted once even when the OBJ loader split them to carry different
+// normals or texture coordinates.
+pub struct ObjStats {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    pub has_normals: bool,
+    pub has_texcoords: bool,
+    pub bounds: Option<(Vec3, Vec3)>,
+    // Names of the named objects and groups, in file order.
+    pub mesh_names: Vec<String>,
+}
+
+impl fmt::Display for ObjStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} vertices, {} triangles, normals: {}, texcoords: {}",
+            self.vertex_count,
+            self.triangle_count,
+            if self.has_normals { "yes" } else { "no" },
+            if self.has_texcoords { "yes" } else { "no" },
+        )?;
+        match self.bounds {
+            Some((min, max)) => write!(
+                f,
+                ", bounds: ({:.3}, {:.3}, {:.3}) to ({:.3}, {:.3}, {:.3})",
+                min.x, min.y, min.z, max.x, max.y, max.z
+            ),
+            None => write!(f, ", bounds: empty"),
+        }?;
+        if !self.mesh_names.is_empty() {
+            write!(f, ", meshes: {}", self.mesh_names.join(", "))?;
+        }
+        Ok(())
+    }
 }
 
-struct Mesh {
+pub struct Mesh {
+    name: Option<String>,
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
+    colors: Vec<Color>,
+    ao: Vec<f32>,
     indices: Vec<u32>,
+    material_id: Option<usize>,
 }
 
 impl Obj {
     pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
+        let (models, materials) = tobj::load_obj(filename, &tobj::LoadOptions {
             single_index: true,
             triangulate: true,
             ..Default::default()
         })?;
 
+        // A missing or broken MTL just leaves the model without materials.
+        // Texture paths are relative to the OBJ file.
+        let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+        let materials = materials.unwrap_or_default().into_iter().map(|material| Material {
+            emissive_map: material.unknown_param.get("map_Ke")
+                // Options such as `-bm 1` come before the file name.
+                .and_then(|value| value.split_whitespace().last())
+                .map(|path| Texture::from_png(&directory.join(path).to_string_lossy(), MISSING_EMISSIVE_COLOR)),
+        }).collect();
+
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
             Mesh {
+                name: Some(model.name).filter(|name| !name.is_empty() && name != "unnamed_object"),
                 vertices: mesh.positions.chunks(3)
                     .map(|v| Vec3::new(v[0], v[1], v[2]))
                     .collect(),
                 normals: mesh.normals.chunks(3)
                     .map(|n| Vec3::new(n[0], n[1], n[2]))
                     .collect(),
+                // OBJ puts v = 0 at the bottom of the image; flip it so v = 0 is
+                // the top row, matching the framebuffer and Texture::sample.
                 texcoords: mesh.texcoords.chunks(2)
                     .map(|t| Vec2::new(t[0], 1.0 - t[1]))
                     .collect(),
+                // Extended `v x y z r g b` lines; tobj leaves this empty when
+                // the file has no vertex colors.
+                colors: mesh.vertex_color.chunks(3)
+                    .map(|c| Color::new(channel(c[0]), channel(c[1]), channel(c[2])))
+                    .collect(),
+                ao: Vec::new(),
                 indices: mesh.indices,
+                material_id: mesh.material_id,
             }
         }).collect();
 
-        Ok(Obj { meshes })
+        Ok(Obj { meshes, materials })
     }
 
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
-        let mut vertices = Vec::new();
+    // Precomputes per-vertex ambient occlusion by casting `samples` hemisphere
+    // rays from each vertex against the mesh's own triangles. The result is the
+    // fraction of rays that escape, so 1.0 means fully exposed. Only geometry
+    // within a quarter of the mesh's bounding radius counts as occluding, which
+    // keeps the bake local and cheap.
+    pub fn bake_ao(&mut self, samples: usize) {
+        let mut rng = Rng::new(0);
+
+        for mesh in &mut self.meshes {
+            let triangles: Vec<[Vec3; 3]> = mesh.indices.chunks(3)
+                .filter(|tri| tri.len() == 3)
+                .map(|tri| [
+                    mesh.vertices[tri[0] as usize],
+                    mesh.vertices[tri[1] as usize],
+                    mesh.vertices[tri[2] as usize],
+                ])
+                .collect();
+            let bounds: Vec<(Vec3, f32)> = triangles.iter().map(|tri| {
+                let center = (tri[0] + tri[1] + tri[2]) / 3.0;
+                let radius = tri.iter().map(|v| (v - center).magnitude()).fold(0.0, f32::max);
+                (center, radius)
+            }).collect();
+
+            let centroid = mesh.vertices.iter().fold(Vec3::zeros(), |acc, v| acc + v)
+                / mesh.vertices.len().max(1) as f32;
+            let mesh_radius = mesh.vertices.iter().map(|v| (v - centroid).magnitude()).fold(0.0, f32::max);
+            let ao_radius = mesh_radius * 0.25;
 
-        for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
-                    .cloned()
+            mesh.ao = mesh.vertices.iter().enumerate().map(|(i, &position)| {
+                let normal = mesh.normals.get(i)
+                    .map(|n| n.normalize())
+                    .filter(|n| n.iter().all(|c| c.is_finite()))
                     .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec2::new(0.0, 0.0));
+                let origin = position + normal * 1e-4;
+
+                let nearby: Vec<&[Vec3; 3]> = triangles.iter().zip(&bounds)
+                    .filter(|(_, (center, radius))| (center - origin).magnitude() <= ao_radius + radius)
+                    .map(|(tri, _)| tri)
+                    .collect();
+
+                let unoccluded = (0..samples).filter(|_| {
+                    let direction = random_hemisphere_direction(&mut rng, &normal);
+                    !nearby.iter().any(|tri| ray_hits_triangle(&origin, &direction, tri, ao_radius))
+                }).count();
+
+                unoccluded as f32 / samples.max(1) as f32
+            }).collect();
+        }
+    }
+
+    // Merges vertices closer than `epsilon`, so near-duplicates left by an
+    // exporter stop causing cracks and doubled normals. Vertices that meet
+    // but carry different texture coordinates are snapped to one position
+    // and kept apart, so UV seams survive. Normals are then rebuilt from the
+    // area-weighted faces around each welded position, which makes both sides
+    // of a seam shade alike. Collapsed triangles are dropped, and baked AO is
+    // discarded because its vertices no longer line up.
+    pub fn weld(&mut self, epsilon: f32) {
+        for mesh in &mut self.meshes {
+            mesh.weld(epsilon);
+        }
+    }
+
+    // Each material's emissive map, indexed like `Vertex::material_id`.
+    pub fn emissive_maps(&self) -> Vec<Option<&Texture>> {
+        self.materials.iter().map(|material| material.emissive_map.as_ref()).collect()
+    }
+
+    pub fn stats(&self) -> ObjStats {
+        let positions = self.meshes.iter().flat_map(|mesh| mesh.vertices.iter());
+        let unique: HashSet<[u32; 3]> = positions.clone()
+            .map(|v| [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()])
+            .collect();
+        let bounds = positions.fold(None, |bounds: Option<(Vec3, Vec3)>, v| match bounds {
+            Some((min, max)) => Some((min.inf(v), max.sup(v))),
+            None => Some((*v, *v)),
+        });
+
+        ObjStats {
+            vertex_count: unique.len(),
+            triangle_count: self.meshes.iter().map(|mesh| mesh.indices.len() / 3).sum(),
+            has_normals: self.meshes.iter().any(|mesh| !mesh.normals.is_empty()),
+            has_texcoords: self.meshes.iter().any(|mesh| !mesh.texcoords.is_empty()),
+            bounds,
+            mesh_names: self.meshes.iter().filter_map(Mesh::name).map(str::to_string).collect(),
+        }
+    }
+
+    pub fn meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+        self.meshes().iter().flat_map(Mesh::get_vertex_array).collect()
+    }
+}
+
+impl Mesh {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn weld(&mut self, epsilon: f32) {
+        let epsilon = epsilon.max(0.0);
+        let cell_size = epsilon.max(f32::MIN_POSITIVE);
+        let cell = |v: &Vec3| [v.x, v.y, v.z].map(|c| (c / cell_size).floor() as i64);
+
+        // Each vertex joins the first earlier representative within
+        // `epsilon`, found by checking the 27 grid cells around it.
+        let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut cluster = Vec::with_capacity(self.vertices.len());
+        for (i, v) in self.vertices.iter().enumerate() {
+            let [cx, cy, cz] = cell(v);
+            let found = (-1..=1)
+                .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [dx, dy, dz])))
+                .filter_map(|[dx, dy, dz]| grid.get(&[cx.saturating_add(dx), cy.saturating_add(dy), cz.saturating_add(dz)]))
+                .flatten()
+                .copied()
+                .find(|&r| (self.vertices[r] - v).magnitude() <= epsilon);
+            cluster.push(found.unwrap_or_else(|| {
+                grid.entry([cx, cy, cz]).or_default().push(i);
+                i
+            }));
+        }
+
+        // Within a cluster, vertices with matching texture coordinates merge.
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut order: Vec<usize> = Vec::new();
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        for (i, &rep) in cluster.iter().enumerate() {
+            let group = groups.entry(rep).or_default();
+            let same = group.iter().copied().find(|&k| match (self.texcoords.get(order[k]), self.texcoords.get(i)) {
+                (Some(a), Some(b)) => (a - b).magnitude() <= epsilon,
+                _ => true,
+            });
+            remap.push(same.unwrap_or_else(|| {
+                group.push(order.len());
+                order.push(i);
+                order.len() - 1
+            }) as u32);
+        }
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+        let indices: Vec<u32> = self.indices.chunks_exact(3)
+            .map(|tri| [remap[tri[0] as usize], remap[tri[1] as usize], remap[tri[2] as usize]])
+            .filter(|tri| tri[0] != tri[1] && tri[1] != tri[2] && tri[0] != tri[2])
+            .flatten()
+            .collect();
+        let vertices: Vec<Vec3> = order.iter().map(|&i| self.vertices[cluster[i]]).collect();
+
+        let mut sums = vec![Vec3::zeros(); cluster.len()];
+        for tri in indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|k| vertices[k as usize]);
+            let face = (b - a).cross(&(c - a));
+            for &k in tri {
+                sums[cluster[order[k as usize]]] += face;
             }
         }
+        let normals = order.iter().map(|&i| {
+            sums[cluster[i]].try_normalize(f32::EPSILON)
+                .or_else(|| self.normals.get(i).copied())
+                .unwrap_or(Vec3::new(0.0, 1.0, 0.0))
+        }).collect();
+
+        self.texcoords = reorder(&self.texcoords, &order);
+        self.colors = reorder(&self.colors, &order);
+        self.normals = normals;
+        self.vertices = vertices;
+        self.indices = indices;
+        self.ao = Vec::new();
+    }
+
+    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+        let mut vertices = Vec::with_capacity(self.indices.len());
+
+        for &index in &self.indices {
+            let position = self.vertices[index as usize];
+            let normal = self.normals.get(index as usize)
+                .cloned()
+                .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+            let tex_coords = self.texcoords.get(index as usize)
+                .cloned()
+                .unwrap_or(Vec2::new(0.0, 0.0));
+
+            let mut vertex = Vertex::new(position, normal, tex_coords);
+            vertex.ao = self.ao.get(index as usize).cloned().unwrap_or(1.0);
+            vertex.color = self.colors.get(index as usize)
+                .cloned()
+                .unwrap_or(Color::new(255, 255, 255));
+            vertex.material_id = self.material_id;
+            vertices.push(vertex);
+        }
 
         vertices
     }
 }
+
+// The entries of `values` at `order`, leaving an absent attribute empty.
+fn reorder<T: Copy>(values: &[T], order: &[usize]) -> Vec<T> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    order.iter().map(|&i| values[i]).collect()
+}
+
+fn channel(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn random_hemisphere_direction(rng: &mut Rng, normal: &Vec3) -> Vec3 {
+    let direction = rng.next_unit_vec3();
+    if direction.dot(normal) < 0.0 { -direction } else { direction }
+}
+
+// Moller-Trumbore intersection, only counting hits in front of the origin and
+// no farther than `max_distance` along the (unit) direction.
+fn ray_hits_triangle(origin: &Vec3, direction: &Vec3, triangle: &[Vec3; 3], max_distance: f32) -> bool {
+    let edge1 = triangle[1] - triangle[0];
+    let edge2 = triangle[2] - triangle[0];
+    let p = direction.cross(&edge2);
+    let det = edge1.dot(&p);
+    if det.abs() < 1e-8 {
+        return false;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = origin - triangle[0];
+    let u = s.dot(&p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return false;
+    }
+
+    let q = s.cross(&edge1);
+    let v = direction.dot(&q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return false;
+    }
+
+    let t = edge2.dot(&q) * inv_det;
+    t > 1e-4 && t <= max_distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{Filter, Wrap};
+    use std::fs::{self, File};
+
+    fn quad_with_near_duplicate() -> Mesh {
+        // Two triangles whose shared corner at x = 1 is split into
+        // vertices 1 and 3, 1e-6 apart.
+        Mesh {
+            name: None,
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(1.0 + 1e-6, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+            ],
+            normals: Vec::new(),
+            texcoords: Vec::new(),
+            colors: Vec::new(),
+            ao: Vec::new(),
+            indices: vec![0, 1, 2, 3, 4, 2],
+            material_id: None,
+        }
+    }
+
+    // Loads `source` through a temporary file, since tobj reads from disk.
+    fn load_source(name: &str, source: &str) -> Obj {
+        let directory = std::env::temp_dir().join(format!("lab4_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("model.obj"), source).unwrap();
+        let obj = Obj::load(&directory.join("model.obj").to_string_lossy()).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        obj
+    }
+
+    #[test]
+    fn weld_merges_within_epsilon_only() {
+        let mut coarse = quad_with_near_duplicate();
+        coarse.weld(1e-4);
+        assert_eq!(coarse.vertices.len(), 4);
+        assert_eq!(coarse.indices[1], coarse.indices[3]);
+        assert!(coarse.normals.iter().all(|n| (n - Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-6));
+
+        let mut fine = quad_with_near_duplicate();
+        fine.weld(1e-8);
+        assert_eq!(fine.vertices.len(), 5);
+        assert_ne!(fine.indices[1], fine.indices[3]);
+    }
+
+    #[test]
+    fn map_ke_glows_only_over_emissive_texels_of_its_material() {
+        let directory = std::env::temp_dir().join(format!("lab4_map_ke_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        // A 2x1 map: bright on the left texel, black on the right.
+        let mut encoder = png::Encoder::new(File::create(directory.join("glow.png")).unwrap(), 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[255, 200, 0, 0, 0, 0]).unwrap();
+        fs::write(directory.join("test.mtl"), "newmtl glow\nmap_Ke glow.png\nnewmtl plain\nKd 1 1 1\n").unwrap();
+        fs::write(directory.join("test.obj"), "\
+mtllib test.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vt 0.25 0.5
+vt 0.75 0.5
+usemtl glow
+f 1/1 2/1 3/1
+f 2/2 4/2 3/2
+usemtl plain
+f 1/1 3/1 4/1
+").unwrap();
+
+        let obj = Obj::load(&directory.join("test.obj").to_string_lossy()).unwrap();
+        let maps = obj.emissive_maps();
+        let emission = |vertex: &Vertex| vertex.material_id
+            .and_then(|id| maps.get(id).copied().flatten())
+            .map(|map| map.sample(vertex.tex_coords.x, vertex.tex_coords.y, Filter::Nearest, Wrap::Clamp, 0.0));
+
+        let vertices = obj.get_vertex_array();
+        assert_eq!(vertices.len(), 9);
+        let (glowing, unlit) = vertices.split_at(6);
+        for vertex in &glowing[..3] {
+            assert!(emission(vertex).is_some_and(|color| !color.is_black()));
+        }
+        for vertex in &glowing[3..] {
+            assert!(emission(vertex).is_some_and(|color| color.is_black()));
+        }
+        for vertex in unlit {
+            assert!(emission(vertex).is_none());
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn each_object_loads_as_its_own_named_mesh() {
+        let obj = load_source("two_objects", "\
+o first
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 3
+o second
+v 0 0 1
+v 1 0 1
+v 1 1 1
+v 0 1 1
+f 4 5 6 7
+");
+        let meshes = obj.meshes();
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].name(), Some("first"));
+        assert_eq!(meshes[1].name(), Some("second"));
+        assert_eq!(meshes[0].indices.len() / 3, 1);
+        assert_eq!(meshes[1].indices.len() / 3, 2);
+    }
+
+    #[test]
+    fn crease_vertex_is_more_occluded_than_an_exposed_one() {
+        // A floor facing +y ending just short of a wall facing +x at x = 0.
+        let floor = [
+            Vec3::new(0.05, 0.0, -1.0),
+            Vec3::new(2.0, 0.0, -1.0),
+            Vec3::new(2.0, 0.0, 1.0),
+            Vec3::new(0.05, 0.0, 1.0),
+        ];
+        let wall = [
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 2.0, -1.0),
+            Vec3::new(0.0, 2.0, 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        let mesh = Mesh {
+            name: None,
+            vertices: floor.iter().chain(&wall).copied().collect(),
+            normals: [Vec3::new(0.0, 1.0, 0.0); 4].into_iter().chain([Vec3::new(1.0, 0.0, 0.0); 4]).collect(),
+            texcoords: Vec::new(),
+            colors: Vec::new(),
+            ao: Vec::new(),
+            indices: vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7],
+            material_id: None,
+        };
+        let mut obj = Obj { meshes: vec![mesh], materials: Vec::new() };
+        obj.bake_ao(256);
+
+        let ao = &obj.meshes[0].ao;
+        let (crease, exposed) = (ao[0], ao[1]);
+        assert!(exposed > 0.95, "{}", exposed);
+        assert!(crease < exposed - 0.1, "crease {} exposed {}", crease, exposed);
+    }
+
+    #[test]
+    fn vertex_colors_come_from_trailing_rgb_or_default_to_white() {
+        let colored = load_source("colored", "\
+v 0 0 0 1 0 0
+v 1 0 0 0 1 0
+v 0 1 0 0 0 1
+f 1 2 3
+");
+        let colors: Vec<u32> = colored.get_vertex_array().iter().map(|v| v.color.to_hex()).collect();
+        assert_eq!(colors, [0xFF0000, 0x00FF00, 0x0000FF]);
+
+        let plain = load_source("plain", "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 3
+");
+        assert!(plain.meshes[0].colors.is_empty());
+        assert!(plain.get_vertex_array().iter().all(|v| v.color.to_hex() == 0xFFFFFF));
+    }
+
+    #[test]
+    fn cube_stats_count_each_corner_once() {
+        // Per-face normals make tobj split every corner into three vertices.
+        let obj = load_source("cube", "\
+v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+v -1 -1 1
+v 1 -1 1
+v 1 1 1
+v -1 1 1
+vn 0 0 -1
+vn 0 0 1
+vn -1 0 0
+vn 1 0 0
+vn 0 -1 0
+vn 0 1 0
+f 1//1 4//1 3//1 2//1
+f 5//2 6//2 7//2 8//2
+f 1//3 5//3 8//3 4//3
+f 2//4 3//4 7//4 6//4
+f 1//5 2//5 6//5 5//5
+f 4//6 8//6 7//6 3//6
+");
+        let stats = obj.stats();
+        assert!(obj.meshes[0].vertices.len() > 8);
+        assert_eq!(stats.vertex_count, 8);
+        assert_eq!(stats.triangle_count, 12);
+        assert!(stats.has_normals);
+        assert!(!stats.has_texcoords);
+        assert_eq!(stats.bounds, Some((Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0))));
+    }
+
+    #[test]
+    fn top_of_the_model_samples_the_top_row_of_the_texture() {
+        // A quad whose vt follow OBJ's bottom-up convention.
+        let obj = load_source("texcoords", "\
+v -1 -1 0
+v 1 -1 0
+v 1 1 0
+v -1 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+f 1/1 2/2 3/3 4/4
+");
+        // Red top row over a blue bottom row.
+        let texture = Texture::new(1, 2, vec![0xFF0000, 0x0000FF]);
+        for vertex in obj.get_vertex_array() {
+            let sampled = texture.sample(vertex.tex_coords.x, vertex.tex_coords.y, Filter::Nearest, Wrap::Clamp, 0.0);
+            let expected = if vertex.position.y > 0.0 { 0xFF0000 } else { 0x0000FF };
+            assert_eq!(sampled.to_hex(), expected, "vertex at {:?}", vertex.position);
+        }
+    }
+}
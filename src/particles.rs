@@ -0,0 +1,138 @@
+use nalgebra_glm::{Mat4, Vec3};
+use crate::framebuffer::{BlendMode, Framebuffer};
+use crate::rng::Rng;
+
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+}
+
+// Point particles streaming off a comet nucleus. New particles start on the
+// nucleus surface and drift away from the sun with some random spread; they
+// fade out over `lifetime` and are then removed.
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    rng: Rng,
+    pub origin: Vec3,
+    pub radius: f32,
+    pub emit_per_update: usize,
+    pub lifetime: f32,
+    pub speed: f32,
+    pub color: u32,
+}
+
+impl ParticleSystem {
+    pub fn new(origin: Vec3, radius: f32) -> Self {
+        ParticleSystem {
+            particles: Vec::new(),
+            rng: Rng::new(7),
+            origin,
+            radius,
+            emit_per_update: 12,
+            lifetime: 90.0,
+            speed: 0.02,
+            color: 0xB0D8FF,
+        }
+    }
+
+    // Advances every particle by `dt`, drops expired ones and emits a new
+    // batch. `sun_dir` points from the nucleus toward the sun.
+    pub fn update(&mut self, dt: f32, sun_dir: Vec3) {
+        for particle in &mut self.particles {
+            particle.position += particle.velocity * dt;
+            particle.age += dt;
+        }
+        let lifetime = self.lifetime;
+        self.particles.retain(|particle| particle.age < lifetime);
+
+        let away = -sun_dir.normalize();
+        for _ in 0..self.emit_per_update {
+            let jitter = Vec3::new(
+                self.rng.range(-1.0, 1.0),
+                self.rng.range(-1.0, 1.0),
+                self.rng.range(-1.0, 1.0),
+            );
+            let surface = (away + jitter).try_normalize(f32::EPSILON).unwrap_or(away);
+            let velocity = (away + jitter * 0.4) * self.speed * self.rng.range(0.5, 1.5);
+            self.particles.push(Particle {
+                position: self.origin + surface * self.radius,
+                velocity,
+                age: 0.0,
+            });
+        }
+    }
+
+    // Additive soft sprites, depth tested against the scene. `view_proj`
+    // maps world space to clip space; the viewport is the whole framebuffer.
+    pub fn render(&self, framebuffer: &mut Framebuffer, view_proj: &Mat4) {
+        let width = framebuffer.width as f32;
+        let height = framebuffer.height as f32;
+        for particle in &self.particles {
+            let clip = view_proj * particle.position.push(1.0);
+            if clip.w <= f32::EPSILON {
+                continue;
+            }
+            let (x, y, depth) = (clip.x / clip.w, clip.y / clip.w, clip.z / clip.w);
+            if !(-1.0..=1.0).contains(&depth) {
+                continue;
+            }
+            let cx = (x + 1.0) * 0.5 * width;
+            let cy = (1.0 - y) * 0.5 * height;
+
+            let fade = 1.0 - particle.age / self.lifetime;
+            let size = 1.5 + fade;
+            let reach = size.ceil() as i64;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let px = cx.floor() as i64 + dx;
+                    let py = cy.floor() as i64 + dy;
+                    if px < 0 || py < 0 || px as f32 >= width || py as f32 >= height {
+                        continue;
+                    }
+                    let distance = ((px as f32 + 0.5 - cx).powi(2) + (py as f32 + 0.5 - cy).powi(2)).sqrt();
+                    let falloff = (1.0 - distance / size).max(0.0);
+                    let strength = falloff * falloff * fade * 0.5;
+                    if strength <= 0.0 {
+                        continue;
+                    }
+                    let scale = |shift: u32| ((((self.color >> shift) & 0xFF) as f32 * strength) as u32) << shift;
+                    framebuffer.blend_point(BlendMode::Add, px as usize, py as usize, depth, scale(16) | scale(8) | scale(0), strength);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mean_position(system: &ParticleSystem) -> Vec3 {
+        let sum = system.particles.iter().fold(Vec3::zeros(), |sum, particle| sum + particle.position);
+        sum / system.particles.len() as f32
+    }
+
+    #[test]
+    fn particles_drift_away_from_the_sun_and_expire() {
+        let sun_dir = Vec3::new(1.0, 0.0, 0.0);
+        let mut system = ParticleSystem::new(Vec3::zeros(), 0.1);
+        system.lifetime = 10.0;
+        system.update(1.0, sun_dir);
+        let start = mean_position(&system);
+
+        system.emit_per_update = 0;
+        for _ in 0..5 {
+            system.update(1.0, sun_dir);
+        }
+        let moved = mean_position(&system);
+        assert!(moved.x < start.x - 0.05, "{} -> {}", start.x, moved.x);
+        assert!((moved.y - start.y).abs() < (moved.x - start.x).abs());
+        assert_eq!(system.particles.len(), 12);
+
+        for _ in 0..5 {
+            system.update(1.0, sun_dir);
+        }
+        assert!(system.particles.is_empty());
+    }
+}
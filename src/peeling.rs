@@ -0,0 +1,99 @@
+use crate::framebuffer::Framebuffer;
+
+// A translucent sample deferred until opaque geometry is done.
+pub struct TransparentFragment {
+    pub x: usize,
+    pub y: usize,
+    pub depth: f32,
+    pub color: u32,
+    pub alpha: f32,
+}
+
+// Order-independent transparency by depth peeling. Each pass walks all
+// deferred fragments and keeps, per pixel, the nearest one strictly behind
+// the layer peeled in the previous pass and in front of the opaque zbuffer.
+// Layers are accumulated front to back and the result is composited over the
+// framebuffer, so intersecting surfaces blend in the right order without any
+// sorting. Only the nearest `layers` surfaces per pixel contribute.
+pub fn render_transparent_peeled(framebuffer: &mut Framebuffer, fragments: &[TransparentFragment], layers: usize) {
+    let size = framebuffer.width * framebuffer.height;
+    let mut peeled_depth = vec![f32::NEG_INFINITY; size];
+    let mut accum_color = vec![[0.0f32; 3]; size];
+    let mut accum_alpha = vec![0.0f32; size];
+
+    for _ in 0..layers {
+        let mut nearest: Vec<Option<&TransparentFragment>> = vec![None; size];
+        for fragment in fragments {
+            if fragment.x >= framebuffer.width || fragment.y >= framebuffer.height {
+                continue;
+            }
+            let index = fragment.y * framebuffer.width + fragment.x;
+            if fragment.depth <= peeled_depth[index] || fragment.depth >= framebuffer.zbuffer[index] {
+                continue;
+            }
+            if nearest[index].is_none_or(|current| fragment.depth < current.depth) {
+                nearest[index] = Some(fragment);
+            }
+        }
+
+        let mut any = false;
+        for (index, layer) in nearest.iter().enumerate() {
+            let Some(layer) = layer else { continue };
+            any = true;
+            let weight = (1.0 - accum_alpha[index]) * layer.alpha.clamp(0.0, 1.0);
+            for (channel, shift) in accum_color[index].iter_mut().zip([16, 8, 0]) {
+                *channel += weight * ((layer.color >> shift) & 0xFF) as f32;
+            }
+            accum_alpha[index] += weight;
+            peeled_depth[index] = layer.depth;
+        }
+        if !any {
+            break;
+        }
+    }
+
+    for index in 0..size {
+        let alpha = accum_alpha[index];
+        if alpha <= 0.0 {
+            continue;
+        }
+        let dst = framebuffer.buffer[index];
+        let channel = |i: usize, shift: u32| {
+            let value = accum_color[index][i] + (1.0 - alpha) * ((dst >> shift) & 0xFF) as f32;
+            (value.round().clamp(0.0, 255.0) as u32) << shift
+        };
+        framebuffer.buffer[index] = channel(0, 16) | channel(1, 8) | channel(2, 0);
+
+        let coverage = framebuffer.alpha_buffer[index] as f32 / 255.0;
+        framebuffer.alpha_buffer[index] = ((coverage + alpha * (1.0 - coverage)) * 255.0).round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersecting_quads_composite_front_to_back_on_both_sides() {
+        let mut framebuffer = Framebuffer::new(10, 1);
+        framebuffer.clear();
+        // Red gets farther and blue nearer from left to right; they cross
+        // between x = 4 and x = 5.
+        let fragments: Vec<TransparentFragment> = (0..10).flat_map(|x| {
+            let t = x as f32 / 9.0;
+            [
+                TransparentFragment { x, y: 0, depth: 0.1 + 0.8 * t, color: 0xFF0000, alpha: 0.5 },
+                TransparentFragment { x, y: 0, depth: 0.9 - 0.8 * t, color: 0x0000FF, alpha: 0.5 },
+            ]
+        }).collect();
+        render_transparent_peeled(&mut framebuffer, &fragments, 4);
+
+        // Front layer at half strength, the one behind it at a quarter.
+        for x in 0..5 {
+            assert_eq!(framebuffer.buffer[x], 0x800040, "red should lead at x = {}", x);
+        }
+        for x in 5..10 {
+            assert_eq!(framebuffer.buffer[x], 0x400080, "blue should lead at x = {}", x);
+        }
+    }
+}
@@ -0,0 +1,609 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::framebuffer::Framebuffer;
+use crate::color::Color;
+
+const BLOOM_RADIUS: usize = 4;
+const BLOOM_STRENGTH: f32 = 0.6;
+const TONEMAP_EXPOSURE: f32 = 1.0;
+const DISPLAY_GAMMA: f32 = 2.2;
+const VIGNETTE_STRENGTH: f32 = 0.6;
+const VIGNETTE_RADIUS: f32 = 0.5;
+const OUTLINE_COLOR: u32 = 0x000000;
+// Relative depth jump between neighbors that counts as a silhouette.
+const OUTLINE_DEPTH_RATIO: f32 = 0.01;
+
+// The display-side post stages, always applied in the same order: bloom from
+// the emission buffer, tonemap, gamma, outline, vignette, then dither. Each stage can
+// be switched off independently; with everything off the color buffer is left
+// untouched.
+pub struct PostChain {
+    pub bloom: bool,
+    // Emission luminance, 0.0 to 1.0, below which pixels don't glow.
+    pub bloom_threshold: f32,
+    pub tonemap: bool,
+    pub gamma: bool,
+    pub outline: bool,
+    pub vignette: bool,
+    pub dither: bool,
+}
+
+impl Default for PostChain {
+    fn default() -> Self {
+        PostChain {
+            bloom: true,
+            bloom_threshold: 0.0,
+            tonemap: false,
+            gamma: false,
+            outline: false,
+            vignette: false,
+            dither: false,
+        }
+    }
+}
+
+impl PostChain {
+    // Each stage's name and on/off flag, in the order `run` applies them.
+    pub fn stages_mut(&mut self) -> [(&'static str, &mut bool); 6] {
+        [
+            ("Bloom", &mut self.bloom),
+            ("Tonemap", &mut self.tonemap),
+            ("Gamma", &mut self.gamma),
+            ("Outline", &mut self.outline),
+            ("Vignette", &mut self.vignette),
+            ("Dither", &mut self.dither),
+        ]
+    }
+
+    pub fn run(&self, framebuffer: &mut Framebuffer) {
+        if self.bloom {
+            bloom(framebuffer, BLOOM_RADIUS, BLOOM_STRENGTH, self.bloom_threshold);
+        }
+        if self.tonemap {
+            map_channels(framebuffer, |c| c * (1.0 + TONEMAP_EXPOSURE) / (1.0 + c * TONEMAP_EXPOSURE));
+        }
+        if self.gamma {
+            map_channels(framebuffer, |c| c.powf(1.0 / DISPLAY_GAMMA));
+        }
+        if self.outline {
+            outline(framebuffer, OUTLINE_COLOR);
+        }
+        if self.vignette {
+            vignette(framebuffer, VIGNETTE_STRENGTH, VIGNETTE_RADIUS);
+        }
+        if self.dither {
+            dither(framebuffer);
+        }
+    }
+}
+
+// Composites emission over the color buffer, then adds a box-blurred copy of
+// it so emissive pixels glow into their surroundings. Only emission whose
+// luminance reaches `threshold` (0.0 to 1.0) feeds the glow.
+fn bloom(framebuffer: &mut Framebuffer, radius: usize, strength: f32, threshold: f32) {
+    post_process(framebuffer);
+
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let channels = |pixel: u32| [((pixel >> 16) & 0xFF) as f32, ((pixel >> 8) & 0xFF) as f32, (pixel & 0xFF) as f32];
+    let emission: Vec<[f32; 3]> = framebuffer.emission_buffer.iter().map(|&e| {
+        if Color::from_hex(e).luminance() < threshold { [0.0; 3] } else { channels(e) }
+    }).collect();
+
+    let horizontal = box_blur(&emission, width, height, radius, (1, 0));
+    let glow = box_blur(&horizontal, width, height, radius, (0, 1));
+
+    for (pixel, glow) in framebuffer.buffer.iter_mut().zip(&glow) {
+        let base = channels(*pixel);
+        let channel = |i: usize| ((base[i] + glow[i] * strength).round() as u32).min(255);
+        *pixel = (channel(0) << 16) | (channel(1) << 8) | channel(2);
+    }
+}
+
+fn box_blur(source: &[[f32; 3]], width: usize, height: usize, radius: usize, (step_x, step_y): (usize, usize)) -> Vec<[f32; 3]> {
+    let radius = radius as i64;
+    let taps = (2 * radius + 1) as f32;
+    let mut result = vec![[0.0; 3]; source.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0; 3];
+            for offset in -radius..=radius {
+                let sx = x as i64 + offset * step_x as i64;
+                let sy = y as i64 + offset * step_y as i64;
+                if sx < 0 || sy < 0 || sx >= width as i64 || sy >= height as i64 {
+                    continue;
+                }
+                let sample = source[sy as usize * width + sx as usize];
+                for i in 0..3 {
+                    sum[i] += sample[i];
+                }
+            }
+            result[y * width + x] = [sum[0] / taps, sum[1] / taps, sum[2] / taps];
+        }
+    }
+    result
+}
+
+// Applies `f` to every color channel, normalized to [0, 1].
+fn map_channels(framebuffer: &mut Framebuffer, f: impl Fn(f32) -> f32) {
+    for pixel in framebuffer.buffer.iter_mut() {
+        let channel = |shift: u32| {
+            let c = ((*pixel >> shift) & 0xFF) as f32 / 255.0;
+            ((f(c) * 255.0).round().clamp(0.0, 255.0) as u32) << shift
+        };
+        *pixel = channel(16) | channel(8) | channel(0);
+    }
+}
+
+// Ordered 4x4 Bayer dither of up to one step per channel, breaking up the
+// banding that tonemap and gamma leave in smooth gradients. The buffer is
+// already 8-bit, so offsets span almost a full step either way; anything
+// under half a step would just round back to the same value.
+fn dither(framebuffer: &mut Framebuffer) {
+    const BAYER: [[f32; 4]; 4] = [
+        [0.0, 8.0, 2.0, 10.0],
+        [12.0, 4.0, 14.0, 6.0],
+        [3.0, 11.0, 1.0, 9.0],
+        [15.0, 7.0, 13.0, 5.0],
+    ];
+    let width = framebuffer.width;
+    for (index, pixel) in framebuffer.buffer.iter_mut().enumerate() {
+        let offset = (BAYER[(index / width) % 4][(index % width) % 4] + 0.5) / 8.0 - 1.0;
+        let channel = |shift: u32| {
+            let c = ((*pixel >> shift) & 0xFF) as f32 + offset;
+            (c.round().clamp(0.0, 255.0) as u32) << shift
+        };
+        *pixel = channel(16) | channel(8) | channel(0);
+    }
+}
+
+pub fn post_process(framebuffer: &mut Framebuffer) {
+    for (pixel, emission) in framebuffer.buffer.iter_mut().zip(&framebuffer.emission_buffer) {
+        if *emission != 0 {
+            *pixel = blend_emission(*pixel, *emission);
+        }
+    }
+}
+
+fn blend_emission(color: u32, emission: u32) -> u32 {
+    let r1 = (color >> 16) & 0xFF;
+    let g1 = (color >> 8) & 0xFF;
+    let b1 = color & 0xFF;
+
+    let r2 = (emission >> 16) & 0xFF;
+    let g2 = (emission >> 8) & 0xFF;
+    let b2 = emission & 0xFF;
+
+    let blend = |c1, c2| ((c1 as f32 * 0.8) + (c2 as f32 * 0.2)).min(255.0) as u32;
+
+    let r = blend(r1, r2);
+    let g = blend(g1, g2);
+    let b = blend(b1, b2);
+
+    (r << 16) | (g << 8) | b
+}
+
+// Rebuilds view-space normals purely from the depth buffer so shading can be
+// checked without trusting the mesh normals. `proj_inv` maps screen space
+// (pixel x, pixel y, depth) back to view space.
+pub fn reconstruct_normals_from_depth(framebuffer: &Framebuffer, proj_inv: &Mat4) -> Vec<u32> {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let mut normals = vec![0; width * height];
+
+    let view_position = |x: usize, y: usize| -> Option<Vec3> {
+        let depth = framebuffer.zbuffer[y * width + x];
+        if !depth.is_finite() {
+            return None;
+        }
+        let p = proj_inv * Vec4::new(x as f32 + 0.5, y as f32 + 0.5, depth, 1.0);
+        Some(Vec3::new(p.x / p.w, p.y / p.w, p.z / p.w))
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let center = match view_position(x, y) {
+                Some(p) => p,
+                None => continue,
+            };
+
+            let ddx = neighbor_delta(center, x, width, |nx| view_position(nx, y));
+            let ddy = neighbor_delta(center, y, height, |ny| view_position(x, ny));
+            let (ddx, ddy) = match (ddx, ddy) {
+                (Some(dx), Some(dy)) => (dx, dy),
+                _ => continue,
+            };
+
+            let mut normal = ddx.cross(&ddy);
+            if normal.magnitude() < 1e-12 {
+                continue;
+            }
+            normal = normal.normalize();
+            if normal.z < 0.0 {
+                normal = -normal;
+            }
+
+            let to_channel = |c: f32| ((c * 0.5 + 0.5) * 255.0).clamp(0.0, 255.0) as u32;
+            normals[y * width + x] = (to_channel(normal.x) << 16) | (to_channel(normal.y) << 8) | to_channel(normal.z);
+        }
+    }
+
+    normals
+}
+
+// Forward difference where possible, falling back to a backward difference at
+// the far edge or a silhouette, so derivatives never mix foreground with sky.
+fn neighbor_delta(center: Vec3, coord: usize, limit: usize, sample: impl Fn(usize) -> Option<Vec3>) -> Option<Vec3> {
+    if coord + 1 < limit {
+        if let Some(next) = sample(coord + 1) {
+            return Some(next - center);
+        }
+    }
+    if coord > 0 {
+        if let Some(prev) = sample(coord - 1) {
+            return Some(center - prev);
+        }
+    }
+    None
+}
+
+// Blurs each pixel by a circle of confusion that grows with its distance from
+// `focus_depth`. Depths are the same normalized values stored in the zbuffer;
+// background pixels (infinite depth) always get the full `max_blur` radius.
+pub fn depth_of_field(framebuffer: &mut Framebuffer, focus_depth: f32, focus_range: f32, max_blur: usize) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let source = framebuffer.buffer.clone();
+
+    let coc: Vec<f32> = framebuffer.zbuffer.iter().map(|&depth| {
+        if depth.is_finite() {
+            ((depth - focus_depth).abs() / focus_range.max(f32::EPSILON)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }).collect();
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let radius = (coc[index] * max_blur as f32).round() as i32;
+            if radius == 0 {
+                continue;
+            }
+
+            let mut sum = [0.0f32; 3];
+            let mut total_weight = 0.0;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if dx * dx + dy * dy > radius * radius {
+                        continue;
+                    }
+                    let sx = x as i32 + dx;
+                    let sy = y as i32 + dy;
+                    if sx < 0 || sy < 0 || sx >= width as i32 || sy >= height as i32 {
+                        continue;
+                    }
+                    let sample_index = sy as usize * width + sx as usize;
+                    // Sharp neighbors contribute little so in-focus edges don't bleed outward.
+                    let weight = if sample_index == index { 1.0 } else { coc[sample_index].max(0.05) };
+                    let sample = source[sample_index];
+                    sum[0] += ((sample >> 16) & 0xFF) as f32 * weight;
+                    sum[1] += ((sample >> 8) & 0xFF) as f32 * weight;
+                    sum[2] += (sample & 0xFF) as f32 * weight;
+                    total_weight += weight;
+                }
+            }
+
+            let channel = |c: f32| ((c / total_weight).round() as u32).min(255);
+            framebuffer.buffer[index] = (channel(sum[0]) << 16) | (channel(sum[1]) << 8) | channel(sum[2]);
+        }
+    }
+}
+
+// Red from the left eye, green and blue from the right, for red/cyan glasses.
+pub fn composite_anaglyph(left: &[u32], right: &[u32]) -> Vec<u32> {
+    left.iter()
+        .zip(right)
+        .map(|(l, r)| (l & 0xFF0000) | (r & 0x00FFFF))
+        .collect()
+}
+
+// Inks geometry pixels on silhouettes and creases: those with a 4-neighbor
+// that is background or noticeably farther away.
+fn outline(framebuffer: &mut Framebuffer, color: u32) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let depth = &framebuffer.zbuffer;
+    let mut edges = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let d = depth[y * width + x];
+            if !d.is_finite() {
+                continue;
+            }
+            let neighbors = [
+                (x > 0).then(|| depth[y * width + x - 1]),
+                (x + 1 < width).then(|| depth[y * width + x + 1]),
+                (y > 0).then(|| depth[(y - 1) * width + x]),
+                (y + 1 < height).then(|| depth[(y + 1) * width + x]),
+            ];
+            let threshold = d.abs().max(f32::EPSILON) * OUTLINE_DEPTH_RATIO;
+            if neighbors.iter().flatten().any(|&n| n - d > threshold) {
+                edges.push(y * width + x);
+            }
+        }
+    }
+    for index in edges {
+        framebuffer.buffer[index] = color;
+    }
+}
+
+// Darkens pixels by their distance from the center, normalized so the corners
+// sit at 1.0. Pixels inside `radius` are left untouched.
+pub fn vignette(framebuffer: &mut Framebuffer, strength: f32, radius: f32) {
+    let cx = framebuffer.width as f32 / 2.0;
+    let cy = framebuffer.height as f32 / 2.0;
+    let max_distance = (cx * cx + cy * cy).sqrt().max(f32::EPSILON);
+
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let dx = x as f32 + 0.5 - cx;
+            let dy = y as f32 + 0.5 - cy;
+            let distance = (dx * dx + dy * dy).sqrt() / max_distance;
+            let t = ((distance - radius) / (1.0 - radius).max(f32::EPSILON)).clamp(0.0, 1.0);
+            let falloff = t * t * (3.0 - 2.0 * t);
+            let factor = 1.0 - strength.clamp(0.0, 1.0) * falloff;
+            if factor >= 1.0 {
+                continue;
+            }
+
+            let index = y * framebuffer.width + x;
+            let pixel = framebuffer.buffer[index];
+            let scale = |shift: u32| ((((pixel >> shift) & 0xFF) as f32 * factor) as u32) << shift;
+            framebuffer.buffer[index] = scale(16) | scale(8) | scale(0);
+        }
+    }
+}
+
+// Smears each moving pixel backwards along its velocity by averaging
+// `samples` taps over the distance it covered since the last frame. Pixels
+// that moved less than half a pixel are left as they are.
+pub fn motion_blur(framebuffer: &mut Framebuffer, samples: usize) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let source = framebuffer.buffer.clone();
+    let samples = samples.max(1);
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let velocity = framebuffer.velocity_buffer[index];
+            if velocity.magnitude() < 0.5 {
+                continue;
+            }
+
+            let mut sum = [0u32; 3];
+            for i in 0..samples {
+                let t = i as f32 / samples as f32;
+                let sx = (x as f32 - velocity.x * t).round().clamp(0.0, width as f32 - 1.0) as usize;
+                let sy = (y as f32 - velocity.y * t).round().clamp(0.0, height as f32 - 1.0) as usize;
+                let sample = source[sy * width + sx];
+                sum[0] += (sample >> 16) & 0xFF;
+                sum[1] += (sample >> 8) & 0xFF;
+                sum[2] += sample & 0xFF;
+            }
+
+            let n = samples as u32;
+            framebuffer.buffer[index] = ((sum[0] / n) << 16) | ((sum[1] / n) << 8) | (sum[2] / n);
+        }
+    }
+}
+
+// Overlays a screen-space reference grid for lining up compositions: a
+// one-pixel line every `spacing` pixels in both directions, starting at the
+// top-left corner, blended over the image at `opacity`.
+pub fn draw_screen_grid(framebuffer: &mut Framebuffer, spacing: usize, color: u32, opacity: f32) {
+    let spacing = spacing.max(1);
+    let opacity = opacity.clamp(0.0, 1.0);
+    let line = Color::from_hex(color);
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            if x % spacing != 0 && y % spacing != 0 {
+                continue;
+            }
+            let index = y * framebuffer.width + x;
+            let pixel = Color::from_hex(framebuffer.buffer[index]);
+            framebuffer.buffer[index] = pixel.lerp(&line, opacity).to_hex();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    #[test]
+    fn screen_grid_lines_sit_every_spacing_pixels_at_the_given_opacity() {
+        let mut framebuffer = Framebuffer::new(800, 10);
+        draw_screen_grid(&mut framebuffer, 100, 0xFFFFFF, 0.25);
+
+        let line = Color::from_hex(0x000000).lerp(&Color::from_hex(0xFFFFFF), 0.25).to_hex();
+        assert!(line != 0x000000 && line != 0xFFFFFF);
+        // Row 0 is a horizontal line; below it only the vertical lines show.
+        assert!(framebuffer.buffer[..800].iter().all(|&pixel| pixel == line));
+        for x in 0..800 {
+            let expected = if x % 100 == 0 { line } else { 0x000000 };
+            assert_eq!(framebuffer.buffer[5 * 800 + x], expected, "pixel at x = {}", x);
+        }
+    }
+
+    #[test]
+    fn chain_with_every_stage_off_is_untouched_and_bloom_spreads_emission() {
+        let mut framebuffer = Framebuffer::new(16, 16);
+        framebuffer.set_background_color(0x406080);
+        framebuffer.clear();
+        framebuffer.emission_buffer[8 * 16 + 8] = 0xFFFFFF;
+        let before = framebuffer.buffer.clone();
+
+        let off = PostChain { bloom: false, ..PostChain::default() };
+        off.run(&mut framebuffer);
+        assert_eq!(framebuffer.buffer, before);
+
+        let bloom_only = PostChain::default();
+        assert!(bloom_only.bloom && !bloom_only.tonemap && !bloom_only.gamma);
+        assert!(!bloom_only.outline && !bloom_only.vignette && !bloom_only.dither);
+        bloom_only.run(&mut framebuffer);
+        let brighter = |index: usize| framebuffer.buffer[index] != before[index];
+        assert!(brighter(8 * 16 + 10));
+        assert!(brighter(6 * 16 + 8));
+        assert!(!brighter(0));
+    }
+
+    #[test]
+    fn dither_moves_flat_8_bit_values_by_at_most_one_step() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.buffer.fill(0x808080);
+        dither(&mut framebuffer);
+        assert!(framebuffer.buffer.iter().any(|&pixel| pixel != 0x808080));
+        assert!(framebuffer.buffer.iter().all(|&pixel| (0x7F7F7F..=0x818181).contains(&pixel)));
+    }
+
+    #[test]
+    fn emission_below_the_bloom_threshold_does_not_glow() {
+        let glow_beside = |emission: u32| {
+            let mut framebuffer = Framebuffer::new(16, 16);
+            framebuffer.emission_buffer[8 * 16 + 8] = emission;
+            let chain = PostChain { bloom_threshold: 0.5, ..PostChain::default() };
+            chain.run(&mut framebuffer);
+            framebuffer.buffer[8 * 16 + 10]
+        };
+        assert_eq!(glow_beside(0x303030), 0x000000);
+        assert_ne!(glow_beside(0xF0F0F0), 0x000000);
+    }
+
+    #[test]
+    fn toggled_stage_changes_the_output_only_while_enabled() {
+        // Mid-gray background with an emissive pixel inside a block of
+        // geometry, so every stage has something to change.
+        let mut input = Framebuffer::new(16, 16);
+        input.set_background_color(0x606060);
+        input.clear();
+        for y in 4..12 {
+            for x in 4..12 {
+                input.point_with_emission(x, y, 0.5, 0);
+            }
+        }
+        input.emission_buffer[8 * 16 + 8] = 0xFFFFFF;
+        let output = |chain: &PostChain| {
+            let mut framebuffer = input.blank_copy();
+            framebuffer.buffer.clone_from(&input.buffer);
+            framebuffer.zbuffer.clone_from(&input.zbuffer);
+            framebuffer.emission_buffer.clone_from(&input.emission_buffer);
+            chain.run(&mut framebuffer);
+            framebuffer.buffer
+        };
+
+        let mut chain = PostChain { bloom: false, ..PostChain::default() };
+        let untouched = output(&chain);
+        assert_eq!(untouched, input.buffer);
+        let toggle = |chain: &mut PostChain, stage: usize| {
+            let (_, enabled) = &mut chain.stages_mut()[stage];
+            **enabled = !**enabled;
+            **enabled
+        };
+        for stage in 0..chain.stages_mut().len() {
+            let name = chain.stages_mut()[stage].0;
+            assert!(toggle(&mut chain, stage), "{} did not turn on", name);
+            assert_ne!(output(&chain), untouched, "{} changed nothing", name);
+            assert!(!toggle(&mut chain, stage), "{} did not turn off", name);
+            assert_eq!(output(&chain), untouched, "{} still applied after turning off", name);
+        }
+    }
+
+    #[test]
+    fn flat_depth_plane_reconstructs_one_constant_normal() {
+        let mut framebuffer = Framebuffer::new(16, 16);
+        for y in 2..14 {
+            for x in 2..14 {
+                framebuffer.zbuffer[y * 16 + x] = 0.1 + 0.01 * x as f32 + 0.02 * y as f32;
+            }
+        }
+        // With an identity inverse, view space is just (pixel x, pixel y, depth).
+        let normals = reconstruct_normals_from_depth(&framebuffer, &Mat4::identity());
+
+        let interior: Vec<u32> = (3..13).flat_map(|y| (3..13).map(move |x| y * 16 + x)).map(|i| normals[i]).collect();
+        let expected = interior[0];
+        assert!(interior.iter().all(|&normal| normal == expected));
+        assert!(expected & 0xFF > 0xF0, "normal {:06X} should face the viewer", expected);
+        assert_eq!(normals[0], 0);
+    }
+
+    #[test]
+    fn depth_of_field_keeps_focused_pixels_and_softens_a_blurred_edge() {
+        // Black left half, white right half, every pixel at one depth.
+        let edge = |depth: f32| {
+            let mut framebuffer = Framebuffer::new(16, 8);
+            for (index, pixel) in framebuffer.buffer.iter_mut().enumerate() {
+                *pixel = if index % 16 < 8 { 0x000000 } else { 0xFFFFFF };
+            }
+            framebuffer.zbuffer.fill(depth);
+            framebuffer
+        };
+
+        let mut focused = edge(0.5);
+        let before = focused.buffer.clone();
+        depth_of_field(&mut focused, 0.5, 0.1, 3);
+        assert_eq!(focused.buffer, before);
+
+        let mut blurred = edge(0.9);
+        depth_of_field(&mut blurred, 0.5, 0.1, 3);
+        let (dark, light) = (blurred.buffer[4 * 16 + 7], blurred.buffer[4 * 16 + 8]);
+        assert!(dark > 0x000000 && dark & 0xFF < 0xFF, "{:06X}", dark);
+        assert!(light < 0xFFFFFF && light & 0xFF > 0, "{:06X}", light);
+        assert_eq!(blurred.buffer[4 * 16], 0x000000);
+    }
+
+    #[test]
+    fn red_left_eye_and_cyan_right_eye_composite_to_white() {
+        assert_eq!(composite_anaglyph(&[0xFF0000], &[0x00FFFF]), [0xFFFFFF]);
+        assert_eq!(composite_anaglyph(&[0x00FFFF], &[0xFF0000]), [0x000000]);
+    }
+
+    #[test]
+    fn vignette_darkens_corners_more_than_the_center() {
+        let gray = || {
+            let mut framebuffer = Framebuffer::new(32, 32);
+            framebuffer.buffer.fill(0x808080);
+            framebuffer
+        };
+        let mut framebuffer = gray();
+        vignette(&mut framebuffer, 0.6, 0.5);
+        let (corner, center) = (framebuffer.buffer[0] & 0xFF, framebuffer.buffer[16 * 32 + 16] & 0xFF);
+        assert_eq!(center, 0x80);
+        assert!(corner < center, "corner {} center {}", corner, center);
+
+        let mut unchanged = gray();
+        vignette(&mut unchanged, 0.0, 0.5);
+        assert!(unchanged.buffer.iter().all(|&pixel| pixel == 0x808080));
+    }
+
+    #[test]
+    fn motion_blur_streaks_moving_pixels_along_their_velocity() {
+        // Red ramps up to the right, so only horizontal motion changes a pixel.
+        let mut framebuffer = Framebuffer::new(16, 8);
+        for (index, pixel) in framebuffer.buffer.iter_mut().enumerate() {
+            *pixel = ((index % 16) as u32 * 16) << 16;
+        }
+        framebuffer.velocity_buffer[4 * 16 + 10] = Vec2::new(4.0, 0.0);
+        framebuffer.velocity_buffer[4 * 16 + 12] = Vec2::new(0.0, 4.0);
+        let before = framebuffer.buffer.clone();
+        motion_blur(&mut framebuffer, 4);
+
+        // Taps at x = 10, 9, 8 and 7.
+        assert_eq!(framebuffer.buffer[4 * 16 + 10], ((160 + 144 + 128 + 112) / 4) << 16);
+        assert_eq!(framebuffer.buffer[4 * 16 + 12], before[4 * 16 + 12]);
+        for index in (0..framebuffer.buffer.len()).filter(|&i| i != 4 * 16 + 10) {
+            assert_eq!(framebuffer.buffer[index], before[index]);
+        }
+    }
+}
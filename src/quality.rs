@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+pub const MAX_QUALITY: u8 = 3;
+
+// Steps the shader quality down after a run of over-budget frames and back up
+// after a longer run of frames with headroom, so a single spike or lull
+// doesn't make the level flicker.
+pub struct QualityController {
+    target: Duration,
+    level: u8,
+    slow_frames: u32,
+    fast_frames: u32,
+}
+
+const SLOW_FRAMES_TO_DROP: u32 = 5;
+const FAST_FRAMES_TO_RAISE: u32 = 30;
+
+impl QualityController {
+    pub fn new(target: Duration) -> Self {
+        QualityController {
+            target,
+            level: MAX_QUALITY,
+            slow_frames: 0,
+            fast_frames: 0,
+        }
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn update(&mut self, frame_time: Duration) {
+        if frame_time > self.target {
+            self.fast_frames = 0;
+            self.slow_frames += 1;
+            if self.slow_frames >= SLOW_FRAMES_TO_DROP {
+                self.level = self.level.saturating_sub(1);
+                self.slow_frames = 0;
+            }
+        } else if frame_time < self.target * 7 / 10 {
+            self.slow_frames = 0;
+            self.fast_frames += 1;
+            if self.fast_frames >= FAST_FRAMES_TO_RAISE {
+                self.level = (self.level + 1).min(MAX_QUALITY);
+                self.fast_frames = 0;
+            }
+        } else {
+            self.slow_frames = 0;
+            self.fast_frames = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_frames_lower_the_level_and_fast_frames_raise_it() {
+        let target = Duration::from_millis(16);
+        let mut quality = QualityController::new(target);
+        for _ in 0..SLOW_FRAMES_TO_DROP {
+            quality.update(Duration::from_millis(30));
+        }
+        assert_eq!(quality.level(), MAX_QUALITY - 1);
+
+        for _ in 0..FAST_FRAMES_TO_RAISE - 1 {
+            quality.update(Duration::from_millis(5));
+        }
+        assert_eq!(quality.level(), MAX_QUALITY - 1);
+        quality.update(Duration::from_millis(5));
+        assert_eq!(quality.level(), MAX_QUALITY);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use crate::framebuffer::Framebuffer;
+
+// Saves every presented frame as a numbered PNG while active. Each frame is
+// written under a temporary name and renamed once complete, so stopping or
+// quitting at any point leaves only whole files behind.
+pub struct Recorder {
+    directory: String,
+    frames: usize,
+    active: bool,
+}
+
+impl Recorder {
+    pub fn new(directory: &str) -> Self {
+        Recorder {
+            directory: directory.to_string(),
+            frames: 0,
+            active: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    // Starts a new sequence, or finishes the current one.
+    pub fn toggle(&mut self) {
+        if self.active {
+            self.finish();
+        } else {
+            self.active = true;
+            self.frames = 0;
+            println!("Recording to {}/", self.directory);
+        }
+    }
+
+    pub fn capture(&mut self, framebuffer: &Framebuffer) -> Result<(), String> {
+        fs::create_dir_all(&self.directory)
+            .map_err(|err| format!("failed to create {}: {}", self.directory, err))?;
+        let path = format!("{}/frame_{:05}.png", self.directory, self.frames);
+        let partial = format!("{}.part", path);
+        framebuffer.save_png_rgba(&partial)?;
+        fs::rename(&partial, &path).map_err(|err| format!("failed to rename {}: {}", partial, err))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Ends the sequence, if one is running, and reports what was written.
+    pub fn finish(&mut self) {
+        if self.active {
+            self.active = false;
+            println!("Recorded {} frames to {}/", self.frames, self.directory);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn stopping_early_leaves_only_complete_frames() {
+        let directory = std::env::temp_dir().join(format!("lab4_recording_{}", std::process::id()));
+        let mut recorder = Recorder::new(&directory.to_string_lossy());
+        let mut framebuffer = Framebuffer::new(8, 4);
+        framebuffer.set_background_color(0x204060);
+        framebuffer.clear();
+
+        recorder.toggle();
+        for _ in 0..3 {
+            recorder.capture(&framebuffer).unwrap();
+        }
+        recorder.toggle();
+        assert!(!recorder.is_active());
+
+        let mut names: Vec<String> = fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["frame_00000.png", "frame_00001.png", "frame_00002.png"]);
+        for name in &names {
+            let decoder = png::Decoder::new(File::open(directory.join(name)).unwrap());
+            let mut reader = decoder.read_info().unwrap();
+            let mut data = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut data).unwrap();
+            assert_eq!((info.width, info.height), (8, 4));
+            assert_eq!(&data[..4], &[0x20, 0x40, 0x60, 0x00]);
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use crate::framebuffer::Framebuffer;
+
+// A stack of temporary render targets layered over a main framebuffer.
+// Multi-pass effects push a target, render into it, pop it and composite the
+// result wherever they need it; the main framebuffer itself is never popped.
+pub struct RenderContext<'a> {
+    main: &'a mut Framebuffer,
+    targets: Vec<Framebuffer>,
+}
+
+impl<'a> RenderContext<'a> {
+    pub fn new(main: &'a mut Framebuffer) -> Self {
+        RenderContext {
+            main,
+            targets: Vec::new(),
+        }
+    }
+
+    // The target currently being rendered into.
+    pub fn current(&mut self) -> &mut Framebuffer {
+        match self.targets.last_mut() {
+            Some(target) => target,
+            None => self.main,
+        }
+    }
+
+    // Pushes a cleared target with the size and background of the current one
+    // and makes it current.
+    pub fn push_target(&mut self) -> &mut Framebuffer {
+        let target = self.current().blank_copy();
+        self.targets.push(target);
+        self.current()
+    }
+
+    // Removes the top target and returns it, or `None` if only the main
+    // framebuffer is left.
+    pub fn pop_target(&mut self) -> Option<Framebuffer> {
+        self.targets.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::BlendMode;
+
+    #[test]
+    fn popped_target_composites_its_color_into_the_main_buffer() {
+        let mut main = Framebuffer::new(6, 4);
+        main.set_background_color(0x101010);
+        main.clear();
+        let mut context = RenderContext::new(&mut main);
+
+        let target = context.push_target();
+        assert!(target.buffer.iter().all(|&pixel| pixel == 0x101010));
+        target.set_current_color(0x20C040);
+        for y in 0..target.height {
+            for x in 0..target.width {
+                target.point_with_emission(x, y, 0.5, 0);
+            }
+        }
+        let target = context.pop_target().unwrap();
+        assert!(context.pop_target().is_none());
+
+        let current = context.current();
+        assert!(current.buffer.iter().all(|&pixel| pixel == 0x101010));
+        for (index, &color) in target.buffer.iter().enumerate() {
+            current.blend_point(BlendMode::Replace, index % target.width, index / target.width, target.zbuffer[index], color, 1.0);
+        }
+        assert!(main.buffer.iter().all(|&pixel| pixel == 0x20C040));
+    }
+}
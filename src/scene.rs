@@ -0,0 +1,131 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::fs;
+use crate::body::Orbit;
+use crate::framebuffer::Framebuffer;
+use crate::texture::{Texture, MISSING_TEXTURE_COLOR};
+
+#[derive(Debug, Deserialize)]
+pub struct Scene {
+  #[serde(default)]
+  pub background: Background,
+  #[serde(default = "default_sun_position")]
+  pub sun_position: [f32; 3],
+  // Optional equirectangular PNG used for reflections instead of the
+  // background color.
+  #[serde(default)]
+  pub environment: Option<String>,
+  #[serde(default = "default_missing_texture_color")]
+  pub missing_texture_color: HexColor,
+  // Fraction of surface color kept on unlit sides, 0.0 to 1.0.
+  #[serde(default = "default_ambient")]
+  pub ambient: f32,
+  // Optional orbit for the displayed body around its rest position.
+  #[serde(default)]
+  pub orbit: Option<Orbit>,
+  // Glow of the displayed body as a multiple of its surface color, on top of
+  // whatever its shader emits. 0.0 leaves only the sun glowing.
+  #[serde(default)]
+  pub emission: f32,
+}
+
+fn default_sun_position() -> [f32; 3] {
+  [12.0, 4.0, 12.0]
+}
+
+fn default_ambient() -> f32 {
+  0.05
+}
+
+fn default_missing_texture_color() -> HexColor {
+  HexColor(MISSING_TEXTURE_COLOR)
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Background {
+  Solid(HexColor),
+  Gradient { top: HexColor, bottom: HexColor },
+}
+
+impl Default for Background {
+  fn default() -> Self {
+    Background::Solid(HexColor(0x000000))
+  }
+}
+
+// Colors are written as "#RRGGBB" strings in the scene file since JSON has
+// no hex number literals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub u32);
+
+impl TryFrom<String> for HexColor {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    let digits = value.strip_prefix('#').unwrap_or(&value);
+    if digits.len() != 6 {
+      return Err(format!("expected a color like \"#RRGGBB\", got \"{}\"", value));
+    }
+    u32::from_str_radix(digits, 16)
+      .map(HexColor)
+      .map_err(|err| format!("invalid color \"{}\": {}", value, err))
+  }
+}
+
+impl Scene {
+  pub fn load(filename: &str) -> Result<Self, String> {
+    let contents = fs::read_to_string(filename)
+      .map_err(|err| format!("failed to read {}: {}", filename, err))?;
+    Self::from_json(&contents)
+  }
+
+  pub fn from_json(contents: &str) -> Result<Self, String> {
+    serde_json::from_str(contents).map_err(|err| format!("invalid scene: {}", err))
+  }
+
+  pub fn sun_position(&self) -> Vec3 {
+    Vec3::from(self.sun_position)
+  }
+
+  // The scene's environment map, or `None` when reflections should use the
+  // background instead.
+  pub fn environment_texture(&self) -> Option<Texture> {
+    self.environment
+      .as_deref()
+      .map(|path| Texture::from_png(path, self.missing_texture_color.0))
+  }
+
+  pub fn apply(&self, framebuffer: &mut Framebuffer) {
+    match self.background {
+      Background::Solid(color) => framebuffer.set_background_color(color.0),
+      Background::Gradient { top, bottom } => framebuffer.set_background_gradient(top.0, bottom.0),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn background_from_json_fills_the_framebuffer_after_apply() {
+    let mut framebuffer = Framebuffer::new(4, 4);
+    Scene::from_json(r##"{ "background": "#336699" }"##).unwrap().apply(&mut framebuffer);
+    framebuffer.clear();
+    assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0x336699));
+
+    let gradient = r##"{ "background": { "top": "#FF0000", "bottom": "#0000FF" } }"##;
+    Scene::from_json(gradient).unwrap().apply(&mut framebuffer);
+    framebuffer.clear();
+    assert_eq!(framebuffer.buffer[0], 0xFF0000);
+    assert_eq!(framebuffer.buffer[3 * 4], 0x0000FF);
+
+    let defaults = Scene::from_json("{}").unwrap();
+    defaults.apply(&mut framebuffer);
+    framebuffer.clear();
+    assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0x000000));
+    assert!(Scene::from_json(r##"{ "background": "#12345" }"##).is_err());
+  }
+}
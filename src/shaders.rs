@@ -1,11 +1,33 @@
-use nalgebra_glm::{Vec3, Vec4, Mat3, mat4_to_mat3};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat3, Mat4, mat4_to_mat3, dot};
 use crate::vertex::Vertex;
 use crate::Uniforms;
+use crate::quality::MAX_QUALITY;
 use crate::fragment::Fragment;
 use crate::color::Color;
 use fastnoise_lite::FastNoiseLite;
+use std::f32::consts::PI;
+
+// Where fragment shaders are evaluated: once per vertex with the resulting
+// colors interpolated across each triangle (Gouraud), or once per pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingFrequency {
+  PerVertex,
+  #[default]
+  PerFragment,
+}
+
+impl ShadingFrequency {
+  pub fn next(self) -> Self {
+    match self {
+      ShadingFrequency::PerVertex => ShadingFrequency::PerFragment,
+      ShadingFrequency::PerFragment => ShadingFrequency::PerVertex,
+    }
+  }
+}
+
 
 
+const MIN_CLIP_W: f32 = 1e-6;
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
   let mut position = Vec4::new(
@@ -24,36 +46,172 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
       * uniforms.model_matrix
       * position;
 
+  // A vertex on the camera plane has no screen position; flag it instead of
+  // dividing so no infinities reach the rasterizer.
   let w = transformed.w;
-  let transformed_position = Vec4::new(
-      transformed.x / w,
-      transformed.y / w,
-      transformed.z / w,
-      1.0,
-  );
+  let is_clipped = w.abs() < MIN_CLIP_W;
+  let screen_position = if is_clipped {
+      Vec4::zeros()
+  } else {
+      uniforms.viewport_matrix * Vec4::new(transformed.x / w, transformed.y / w, transformed.z / w, 1.0)
+  };
 
-  let screen_position = uniforms.viewport_matrix * transformed_position;
+  let previous = uniforms.previous_view_projection
+      * uniforms.previous_model_matrix
+      * position;
+  let previous_screen = uniforms.viewport_matrix
+      * Vec4::new(previous.x / previous.w, previous.y / previous.w, previous.z / previous.w, 1.0);
+  let velocity = Vec2::new(screen_position.x - previous_screen.x, screen_position.y - previous_screen.y);
+  let world_position = uniforms.model_matrix * position;
 
-  let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
-  let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
-  let transformed_normal = normal_matrix * vertex.normal;
+  let normal = safe_normal(vertex.normal);
+  let transformed_normal = safe_normal(uniforms.normal_matrix * normal);
 
   Vertex {
       position: vertex.position,
       normal: vertex.normal,
       tex_coords: vertex.tex_coords,
       color: vertex.color,
+      ao: vertex.ao,
+      roughness: vertex.roughness,
       transformed_position: Vec3::new(
           screen_position.x,
           screen_position.y,
           screen_position.z,
       ),
       transformed_normal,
+      world_position: Vec3::new(world_position.x, world_position.y, world_position.z),
+      clip_position: transformed,
+      w_inv: if is_clipped { 0.0 } else { 1.0 / w },
+      velocity: if !is_clipped && velocity.iter().all(|c| c.is_finite()) { velocity } else { Vec2::zeros() },
+      shaded_color: Color::black(),
+      shaded_emission: Color::black(),
+      shaded_opacity: 0.0,
+      material_id: vertex.material_id,
+      is_clipped,
   }
 }
 
+pub fn create_normal_matrix(model_matrix: &Mat4) -> Option<Mat3> {
+  mat4_to_mat3(model_matrix).transpose().try_inverse()
+}
+
+fn safe_normal(normal: Vec3) -> Vec3 {
+  if normal.magnitude() > 1e-6 && normal.iter().all(|c| c.is_finite()) {
+    normal
+  } else {
+    Vec3::new(0.0, 1.0, 0.0)
+  }
+}
+
+// Looks up the environment map along the view ray mirrored about `normal`,
+// using an equirectangular mapping (v = 0 straight up). A curved mirror has
+// no cheap screen-space derivative, so the base LOD is 0 and `lod_bias`
+// alone selects blurrier mip levels.
+fn sample_environment(uniforms: &Uniforms, world_position: &Vec3, normal: &Vec3) -> Color {
+  let view = (world_position - uniforms.camera_position).normalize();
+  let reflected = view - normal * 2.0 * dot(&view, normal);
+  environment_along(uniforms, &reflected)
+}
+
+fn environment_along(uniforms: &Uniforms, direction: &Vec3) -> Color {
+  let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+  let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+  uniforms.environment.sample(u, v, uniforms.texture_filter, uniforms.texture_wrap, uniforms.lod_bias)
+}
+
+// What a body shader returns for one sample: the lit surface color, the glow
+// it emits on its own for bloom, and an opacity for translucent surfaces such
+// as the comet tail. Opacity is zero for opaque surfaces.
+#[derive(Debug, Clone, Copy)]
+pub struct Shaded {
+  pub color: Color,
+  pub emission: Color,
+  pub opacity: f32,
+}
+
+impl Shaded {
+  pub fn opaque(color: Color) -> Self {
+    Shaded { color, emission: Color::black(), opacity: 0.0 }
+  }
+
+  pub fn lerp(&self, other: &Shaded, t: f32) -> Self {
+    Shaded {
+      color: self.color.lerp(&other.color, t),
+      emission: self.emission.lerp(&other.emission, t),
+      opacity: self.opacity + (other.opacity - self.opacity) * t,
+    }
+  }
+}
+
+// The environment map seen directly, for the inside of a skybox mesh.
+pub fn skybox_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
+  let direction = (fragment.world_position - uniforms.camera_position).normalize();
+  Shaded::opaque(environment_along(uniforms, &direction))
+}
+
+// Inverse-square falloff normalized so the default 5-unit viewing distance
+// reads as 1.0, clamped so flying into the sun doesn't blow up.
+const SUN_GLOW_REFERENCE_DISTANCE: f32 = 5.0;
+const SUN_GLOW_MAX: f32 = 4.0;
+
+// Diffuse intensity lifted by the ambient floor, so the night side keeps
+// `ambient` of its surface color instead of going black.
+fn lit(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  uniforms.ambient + (1.0 - uniforms.ambient) * fragment.intensity
+}
+
+// Blinn-Phong highlight from the sun. Smoother surfaces get a tighter and
+// stronger highlight; a roughness of 1.0 has none.
+pub fn specular(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  let smoothness = 1.0 - fragment.roughness.clamp(0.0, 1.0);
+  if smoothness <= 0.0 || fragment.intensity <= 0.0 {
+    return 0.0;
+  }
+  let to_light = (uniforms.sun_position - fragment.world_position).normalize();
+  let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+  let Some(half) = (to_light + to_camera).try_normalize(f32::EPSILON) else {
+    return 0.0;
+  };
+  let shininess = 4.0 + smoothness * smoothness * 124.0;
+  dot(&fragment.normal, &half).max(0.0).powf(shininess) * smoothness
+}
 
-pub fn sun_shader(uniforms: &Uniforms) -> Color {
+fn sun_glow_intensity(camera_position: &Vec3, sun_position: &Vec3) -> f32 {
+  let distance_sq = (camera_position - sun_position).magnitude_squared();
+  let reference_sq = SUN_GLOW_REFERENCE_DISTANCE * SUN_GLOW_REFERENCE_DISTANCE;
+  (reference_sq / distance_sq.max(f32::EPSILON)).min(SUN_GLOW_MAX)
+}
+
+const GRANULE_SCALE: f32 = 2000.0;
+
+// Brightness of solar granulation at a point on the unit sphere, between
+// about 0.55 in the dark lanes and 1.0 at the center of bright cells. Three
+// octaves of 3D noise give cells of several sizes, and sliding the sample
+// point with `time` makes them churn slowly.
+pub fn granulation(noise: &FastNoiseLite, position: &Vec3, time: u32) -> f32 {
+  let drift = time as f32 * 0.5;
+  let mut value = 0.0;
+  let mut total = 0.0;
+  let mut amplitude = 1.0;
+  let mut frequency = 1.0;
+  for _ in 0..3 {
+    let scale = GRANULE_SCALE * frequency;
+    value += noise.get_noise_3d(position.x * scale + drift, position.y * scale, position.z * scale - drift) * amplitude;
+    total += amplitude;
+    amplitude *= 0.5;
+    frequency *= 2.0;
+  }
+
+  let cells = value / total;
+  // Zero crossings of the noise become the narrow dark lanes between cells.
+  let lane = (cells.abs() / 0.2).min(1.0);
+  0.55 + 0.45 * lane * (0.75 + 0.25 * cells)
+}
+
+// Returns the surface color and, separately, the glow it emits: the corona and
+// halo without the darker core, so bloom picks up only the bright rim light.
+pub fn sun_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
   let pulsate = ((uniforms.time as f32 * 0.01).sin() + 1.0) / 2.0;
 
   let surface_noise = uniforms.noise.get_noise_2d(
@@ -70,9 +228,11 @@ pub fn sun_shader(uniforms: &Uniforms) -> Color {
   let flare_color = Color::new(255, 69, 0);    
   let corona_color = Color::new(255, 255, 160); 
 
-  let core = core_color.lerp(&flare_color, surface_noise);
+  let surface = fragment.vertex_position.normalize();
+  let core = core_color.lerp(&flare_color, surface_noise) * granulation(uniforms.noise, &surface, uniforms.time);
 
-  let corona_intensity = (uniforms.time as f32 * 0.005).cos().abs();
+  let glow = sun_glow_intensity(&uniforms.camera_position, &uniforms.sun_position);
+  let corona_intensity = (uniforms.time as f32 * 0.005).cos().abs() * glow;
   let corona = corona_color * corona_intensity;
 
   let flare_intensity = if eruption_noise > 0.8 {
@@ -84,11 +244,58 @@ pub fn sun_shader(uniforms: &Uniforms) -> Color {
   let final_color = (core + corona) * pulsate * flare_intensity;
 
   let halo_color = Color::new(255, 215, 0); 
-  let halo_intensity = ((uniforms.time as f32 * 0.002).sin().abs() * 0.5).clamp(0.0, 1.0);
+  let halo_intensity = ((uniforms.time as f32 * 0.002).sin().abs() * 0.5 * glow).clamp(0.0, 1.0);
+  let halo = halo_color * halo_intensity;
+  let emission = corona * pulsate * flare_intensity + halo;
+  Shaded { color: final_color + halo, emission, opacity: 0.0 }
+}
+
+// Shades a front-facing point on the corona shell drawn around the sun.
+// `inner_ratio` is the sun's radius as a fraction of the shell's; density
+// falls off from the solar limb to the shell's silhouette and is zero where
+// the shell lies over the sun's disk. The shell is all glow, so its emission is
+// the color weighted by the opacity.
+pub fn corona_shader(fragment: &Fragment, uniforms: &Uniforms, inner_ratio: f32) -> Shaded {
+  let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+  let facing = dot(&fragment.normal, &to_camera);
+  if facing <= 0.0 {
+    return Shaded::opaque(Color::black());
+  }
+
+  let projected_radius = (1.0 - facing * facing).max(0.0).sqrt();
+  if projected_radius <= inner_ratio {
+    return Shaded::opaque(Color::black());
+  }
+
+  let t = ((projected_radius - inner_ratio) / (1.0 - inner_ratio)).clamp(0.0, 1.0);
+  let flicker = uniforms.noise.get_noise_3d(
+      fragment.noise_position.x * 6.0,
+      fragment.noise_position.y * 6.0,
+      uniforms.time as f32 * 0.05,
+  );
+  let density = (1.0 - t).powi(2) * (0.85 + 0.15 * flicker);
+
+  let color = Color::new(255, 200, 120);
+  let opacity = density.clamp(0.0, 1.0) * 0.6;
+  Shaded { color, emission: color * opacity, opacity }
+}
+
+const EARTH_GLINT_SHININESS: f32 = 400.0;
 
-  final_color + halo_color * halo_intensity
+// Mirror reflection of the sun off open water: a tight, near-white spot that
+// only appears where the reflected sun ray points at the camera.
+fn sun_glint(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  let to_light = (uniforms.sun_position - fragment.world_position).normalize();
+  let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+  let facing = dot(&fragment.normal, &to_light);
+  if facing <= 0.0 {
+    return 0.0;
+  }
+  let reflected = fragment.normal * (2.0 * facing) - to_light;
+  dot(&reflected, &to_camera).max(0.0).powf(EARTH_GLINT_SHININESS)
 }
-pub fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+
+pub fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
   let ocean_color = Color::new(0, 102, 204);  
   let land_color = Color::new(34, 139, 34);   
   let desert_color = Color::new(210, 180, 140);  
@@ -99,21 +306,28 @@ pub fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let t_clouds = uniforms.time as f32 * 0.02;
   let t_surface = uniforms.time as f32 * 0.005;
 
+  // Time of day: the continents are looked up on the sphere turned back by
+  // `earth_rotation` about the polar axis, so they slide under the sun while
+  // the clouds keep their own motion.
+  let (sin_r, cos_r) = uniforms.earth_rotation.sin_cos();
+  let p = fragment.noise_position;
+  let surface = Vec3::new(p.x * cos_r - p.z * sin_r, p.y, p.x * sin_r + p.z * cos_r);
+
   let terrain_noise = fbm_noise(
-      &uniforms.noise,
-      fragment.vertex_position.x * 15.0,
-      fragment.vertex_position.y * 15.0,
-      6,  
+      uniforms.noise,
+      surface.x * 15.0,
+      surface.y * 15.0,
+      octaves(6, uniforms.quality),
   );
 
   let biome_noise = uniforms.noise.get_noise_2d(
-      fragment.vertex_position.x * 50.0 + t_surface,
-      fragment.vertex_position.y * 50.0 + t_surface,
+      surface.x * 50.0 + t_surface,
+      surface.y * 50.0 + t_surface,
   );
 
   let cloud_noise = uniforms.noise.get_noise_2d(
-      fragment.vertex_position.x * 100.0 + t_clouds,
-      fragment.vertex_position.y * 100.0 + t_clouds,
+      fragment.noise_position.x * 100.0 + t_clouds,
+      fragment.noise_position.y * 100.0 + t_clouds,
   );
 
   let biome_color = if biome_noise > 0.7 {
@@ -125,7 +339,9 @@ pub fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   };
 
   let surface_color = if biome_noise < 0.3 {
-      ocean_color.lerp(&biome_color, biome_noise / 0.3)  
+      let reflection = sample_environment(uniforms, &fragment.world_position, &fragment.normal);
+      let ocean = ocean_color.lerp(&reflection, 0.35);
+      ocean.lerp(&biome_color, biome_noise / 0.3)  
   } else {
       biome_color  
   };
@@ -138,39 +354,77 @@ pub fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       surface_color  
   };
 
-  let light_factor = 0.6 + 0.4 * terrain_noise * fragment.vertex_position.z.clamp(-1.0, 1.0);
+  let light_factor = 0.6 + 0.4 * terrain_noise * surface.z.clamp(-1.0, 1.0);
 
   let illuminated_color = final_color * light_factor;
-  illuminated_color * fragment.intensity
+  let diffuse = illuminated_color * lit(fragment, uniforms);
+
+  // Clouds hide the water, and the glint fades out with the ocean at coasts.
+  let ocean = if cloud_noise > 0.6 { 0.0 } else { (1.0 - biome_noise / 0.3).clamp(0.0, 1.0) };
+  let color = if ocean <= 0.0 {
+    diffuse
+  } else {
+    diffuse + Color::new(255, 250, 235) * (sun_glint(fragment, uniforms) * ocean)
+  };
+  Shaded { color, emission: earth_aurora(fragment, uniforms), opacity: 0.0 }
 }
 
 
-pub fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+const AURORA_LATITUDE: f32 = 0.85;
+const AURORA_WIDTH: f32 = 0.05;
+
+// Glow of the polar aurorae: a band of green, fringed with pink on its
+// poleward edge, that wavers with longitude and time and only shows on the
+// night side.
+fn earth_aurora(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let surface = fragment.vertex_position.normalize();
+  let to_light = (uniforms.sun_position - fragment.world_position).normalize();
+  let night = (-dot(&fragment.normal, &to_light) * 4.0).clamp(0.0, 1.0);
+  if night <= 0.0 || surface.y.abs() < AURORA_LATITUDE - 3.0 * AURORA_WIDTH {
+    return Color::black();
+  }
+
+  let t = uniforms.time as f32 * 0.3;
+  let longitude = surface.z.atan2(surface.x);
+  let wave = uniforms.noise.get_noise_2d(longitude * 200.0, t) * AURORA_WIDTH;
+  let offset = (surface.y.abs() - AURORA_LATITUDE - wave) / AURORA_WIDTH;
+  let band = (1.0 - offset.abs()).max(0.0);
+  if band <= 0.0 {
+    return Color::black();
+  }
+
+  let flicker = 0.6 + 0.4 * uniforms.noise.get_noise_2d(longitude * 800.0, t * 4.0).abs();
+  let green = Color::new(60, 255, 120);
+  let pink = Color::new(255, 80, 180);
+  green.lerp(&pink, offset.clamp(0.0, 1.0)) * (band * night * flicker * 0.5)
+}
+
+pub fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
   let sand_color = Color::new(210, 77, 38);    
   let rock_color = Color::new(150, 75, 45);    
   let crater_color = Color::new(105, 54, 36);  
   let ridge_color = Color::new(130, 60, 35);   
   let t = uniforms.time as f32 * 0.3;
-  let spherical_x = fragment.vertex_position.x / fragment.vertex_position.z.abs().max(0.1);
-  let spherical_y = fragment.vertex_position.y / fragment.vertex_position.z.abs().max(0.1);
+  let spherical_x = fragment.noise_position.x / fragment.noise_position.z.abs().max(0.1);
+  let spherical_y = fragment.noise_position.y / fragment.noise_position.z.abs().max(0.1);
   let fine_noise = fbm_noise(
-      &uniforms.noise,
+      uniforms.noise,
       spherical_x * 60.0 + t,
       spherical_y * 60.0 + t,
-      5,
+      octaves(5, uniforms.quality),
   );
 
   let crater_noise = uniforms.noise.get_noise_3d(
       spherical_x * 40.0 + t * 0.5,
       spherical_y * 40.0 + t * 0.5,
-      fragment.vertex_position.z * 40.0,
+      fragment.noise_position.z * 40.0,
   );
 
   let rock_noise = fbm_noise(
-      &uniforms.noise,
+      uniforms.noise,
       spherical_x * 20.0,
       spherical_y * 20.0,
-      6,
+      octaves(6, uniforms.quality),
   );
 
   let rock_mask = uniforms.noise.get_noise_2d(
@@ -180,17 +434,15 @@ pub fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let small_rock_factor = (rock_mask * rock_noise).clamp(0.0, 1.0);
 
   let surface_color = if crater_noise > 0.75 {
-      crater_color * (1.0 - crater_noise).clamp(0.5, 1.0) * fragment.intensity
+      crater_color * (1.0 - crater_noise).clamp(0.5, 1.0) * lit(fragment, uniforms)
   } else if small_rock_factor > 0.6 {
-      rock_color * small_rock_factor * fragment.intensity
+      rock_color * small_rock_factor * lit(fragment, uniforms)
   } else {
-      sand_color.lerp(&ridge_color, fine_noise) * fragment.intensity
+      sand_color.lerp(&ridge_color, fine_noise) * lit(fragment, uniforms)
   };
 
   let light_factor = 0.5 + 0.5 * fragment.vertex_position.z.clamp(-1.0, 1.0);
-  let final_color = surface_color * light_factor;
-
-  final_color
+  Shaded::opaque(surface_color * light_factor)
 }
 
 
@@ -210,8 +462,42 @@ fn fbm_noise(noise: &FastNoiseLite, x: f32, y: f32, octaves: usize) -> f32 {
   value
 }
 
+// Scales a shader's full-detail octave count down with the adaptive quality
+// level, never dropping below a single octave.
+fn octaves(full: usize, quality: u8) -> usize {
+  let quality = quality.min(MAX_QUALITY) as usize;
+  (full * (quality + 1) / (MAX_QUALITY as usize + 1)).max(1)
+}
+
+fn domain_warp_fbm(noise: &FastNoiseLite, x: f32, y: f32, octaves: usize, warp_strength: f32) -> f32 {
+  let warp_x = noise.get_noise_2d(x * 0.5 + 5.2, y * 0.5 + 1.3);
+  let warp_y = noise.get_noise_2d(x * 0.5 + 1.7, y * 0.5 + 9.2);
+
+  fbm_noise(noise, x + warp_x * warp_strength, y + warp_y * warp_strength, octaves)
+}
+
+
+// Exponent for gas giant limb darkening; larger values darken the
+// silhouette more aggressively.
+const GAS_GIANT_LIMB_DARKENING: f32 = 0.4;
+
+// Brightness falloff toward the silhouette: 1.0 where the surface faces the
+// viewer, dropping to 0.0 at grazing angles.
+pub fn limb_darkening(normal: &Vec3, view_dir: &Vec3, k: f32) -> f32 {
+  dot(normal, view_dir).max(0.0).powf(k)
+}
+
+const JUPITER_BAND_FREQUENCY: f32 = 15.0;
+const JUPITER_JET_SPEED: f32 = 0.05;
 
-pub fn jupiter_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+// Horizontal drift speed of the cloud band at `latitude` (object-space y).
+// Follows the band pattern, so neighbouring bands flow in opposite
+// directions like Jupiter's alternating eastward and westward jets.
+fn zonal_wind(latitude: f32) -> f32 {
+  JUPITER_JET_SPEED * (latitude * JUPITER_BAND_FREQUENCY).sin()
+}
+
+pub fn jupiter_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
 
   let band_yellow = Color::new(255, 239, 170); 
   let band_beige = Color::new(230, 220, 170);  
@@ -222,19 +508,21 @@ pub fn jupiter_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
   let t = uniforms.time as f32 * 0.02; 
 
-  let y_position = fragment.vertex_position.y * 15.0;
+  let y_position = fragment.noise_position.y * JUPITER_BAND_FREQUENCY;
+  let drift = zonal_wind(fragment.noise_position.y) * t;
 
   let wave_pattern = (y_position + (t * 2.0).sin()).sin(); 
-  let fbm_value = fbm_noise(
-      &uniforms.noise,
-      fragment.vertex_position.x * 1.5 + t * 0.05,
-      fragment.vertex_position.y * 3.0,
-      6,
+  let fbm_value = domain_warp_fbm(
+      uniforms.noise,
+      (fragment.noise_position.x + drift) * 1.5,
+      fragment.noise_position.y * 3.0,
+      octaves(6, uniforms.quality),
+      1.5,
   );
 
   let turbulence = uniforms.noise.get_noise_2d(
-      fragment.vertex_position.x * 10.0 + t * 0.3,
-      fragment.vertex_position.y * 10.0,
+      (fragment.noise_position.x + drift) * 10.0,
+      fragment.noise_position.y * 10.0,
   );
 
   let wave_intensity = ((wave_pattern + fbm_value * 0.5) * 0.5 + 0.5) * (1.0 + turbulence * 0.2);
@@ -252,27 +540,42 @@ pub fn jupiter_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let final_color = if red_spot_intensity > 0.7 {
       storm_color * red_spot_intensity 
   } else {
-      band_color * fragment.intensity 
+      band_color * lit(fragment, uniforms) 
   };
 
-  final_color * (1.0 + 0.15 * turbulence).clamp(0.0, 1.2)
+  let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+  let limb = limb_darkening(&fragment.normal, &to_camera, GAS_GIANT_LIMB_DARKENING);
+  Shaded::opaque(final_color * (1.0 + 0.15 * turbulence).clamp(0.0, 1.2) * limb)
 }
 
 
-pub fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+const MOON_SURGE_STRENGTH: f32 = 0.4;
+// Phase angle in radians over which the surge falls off.
+const MOON_SURGE_WIDTH: f32 = 0.1;
+
+// Opposition surge: regolith brightens sharply as the phase angle between
+// `light_dir` and `view_dir` nears zero, because every grain hides its own
+// shadow. Returns a multiplier of 1.0 at large angles, rising to
+// 1.0 + MOON_SURGE_STRENGTH head-on.
+fn opposition_surge(light_dir: &Vec3, view_dir: &Vec3) -> f32 {
+  let phase_angle = dot(light_dir, view_dir).clamp(-1.0, 1.0).acos();
+  1.0 + MOON_SURGE_STRENGTH * (-phase_angle / MOON_SURGE_WIDTH).exp()
+}
+
+pub fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
   let base_gray = Color::new(180, 180, 180);  
   let crater_edge_color = Color::new(120, 120, 120);  
   let crater_center_color = Color::new(80, 80, 80);  
 
   let t = uniforms.time as f32 * 0.1;  
 
-  let spherical_x = fragment.vertex_position.x / fragment.vertex_position.z.abs().max(0.1);
-  let spherical_y = fragment.vertex_position.y / fragment.vertex_position.z.abs().max(0.1);
-  let crater_noise = fbm_noise(&uniforms.noise, spherical_x * 30.0 + t, spherical_y * 30.0, 4);
-  let mask_noise = fbm_noise(&uniforms.noise, spherical_x * 60.0, spherical_y * 60.0, 5);
+  let spherical_x = fragment.noise_position.x / fragment.noise_position.z.abs().max(0.1);
+  let spherical_y = fragment.noise_position.y / fragment.noise_position.z.abs().max(0.1);
+  let crater_noise = fbm_noise(uniforms.noise, spherical_x * 30.0 + t, spherical_y * 30.0, octaves(4, uniforms.quality));
+  let mask_noise = fbm_noise(uniforms.noise, spherical_x * 60.0, spherical_y * 60.0, octaves(5, uniforms.quality));
   let depth_noise = uniforms.noise.get_noise_2d(
-      fragment.vertex_position.x * 300.0,
-      fragment.vertex_position.y * 300.0,
+      fragment.noise_position.x * 300.0,
+      fragment.noise_position.y * 300.0,
   );
   let crater_effect = if crater_noise > 0.55 && mask_noise > 0.3 {
       crater_center_color.lerp(&crater_edge_color, depth_noise)
@@ -281,12 +584,58 @@ pub fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   };
   let light_factor = 0.5 + 0.5 * fragment.vertex_position.z.clamp(-1.0, 1.0);
   let illuminated_color = crater_effect * light_factor;
-  illuminated_color * fragment.intensity
+  let light_dir = (uniforms.sun_position - fragment.world_position).normalize();
+  let view_dir = (uniforms.camera_position - fragment.world_position).normalize();
+  Shaded::opaque(illuminated_color * (lit(fragment, uniforms) * opposition_surge(&light_dir, &view_dir)))
 }
 
+// Irregular gray rock with pits at several scales and no banding, sampled in
+// 3D so nothing lines up with the poles. `instance_seed` moves each body to
+// its own region of the noise field, so instanced asteroids all differ.
+pub fn asteroid_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
+  let dust_color = Color::new(150, 144, 136);
+  let rock_color = Color::new(98, 94, 90);
+  let pit_color = Color::new(52, 50, 48);
+
+  let seed = uniforms.instance_seed * 1000.0;
+  let p = fragment.vertex_position.normalize() * 300.0 + Vec3::new(seed, seed * 0.7, -seed * 1.3);
+  let noise = |scale: f32| uniforms.noise.get_noise_3d(p.x * scale, p.y * scale, p.z * scale);
+
+  let coarse = noise(1.0) * 0.5 + 0.5;
+  let grain = noise(8.0);
+  // Noise peaks become crater bowls, deepest at the center.
+  let craters = ((noise(2.0) - 0.45) / 0.3).clamp(0.0, 1.0).max(((noise(5.0) - 0.55) / 0.25).clamp(0.0, 1.0) * 0.7);
+
+  let surface = rock_color.lerp(&dust_color, coarse) * (0.85 + 0.15 * grain);
+  Shaded::opaque(surface.lerp(&pit_color, craters * 0.8) * lit(fragment, uniforms))
+}
 
+const SATURN_RING_INNER: f32 = 1.0;
+const SATURN_RING_OUTER: f32 = 2.5;
+const SATURN_RING_SHADOW: f32 = 0.35;
+
+// Fraction of sunlight reaching `position` past a ring lying in the y = 0
+// plane between `ring_inner` and `ring_outer`. Both `position` and `sun_dir`
+// are in the planet's local frame; the result is 1.0 when the point is lit
+// and SATURN_RING_SHADOW when the ring blocks the sun.
+pub fn ring_shadow_factor(position: Vec3, sun_dir: Vec3, ring_inner: f32, ring_outer: f32) -> f32 {
+  if sun_dir.y.abs() < 1e-6 {
+    return 1.0;
+  }
+  let t = -position.y / sun_dir.y;
+  if t <= 0.0 {
+    return 1.0;
+  }
+  let hit = position + sun_dir * t;
+  let hit_radius = (hit.x * hit.x + hit.z * hit.z).sqrt();
+  if hit_radius > ring_inner && hit_radius < ring_outer {
+    SATURN_RING_SHADOW
+  } else {
+    1.0
+  }
+}
 
-pub fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
   let band_color1 = Color::new(210, 180, 140);  
   let band_color2 = Color::new(170, 140, 110);  
   let band_color3 = Color::new(240, 230, 140);  
@@ -302,10 +651,10 @@ pub fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
 
   let ring_noise = fbm_noise(
-      &uniforms.noise,
+      uniforms.noise,
       radius * 10.0,
       angle * 5.0 + uniforms.time as f32 * 0.05,  
-      4, 
+      octaves(4, uniforms.quality),
   );
 
   let wave_pattern = (radius * 6.0 + (uniforms.time as f32 * 0.1).sin()).sin();
@@ -323,10 +672,10 @@ pub fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let latitude = pos.y;
 
   let band_noise = fbm_noise(
-      &uniforms.noise,
+      uniforms.noise,
       latitude * 25.0 + uniforms.time as f32 * 0.01, 
       0.0,
-      6,  
+      octaves(6, uniforms.quality),
   );
 
   let band_color = if band_noise > 0.75 {
@@ -339,18 +688,85 @@ pub fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       band_color4
   };
 
-  let ring_opacity = ((2.5 - radius).clamp(0.0, 1.0) * 0.8).max(0.1);
+  let ring_opacity = ((SATURN_RING_OUTER - radius).clamp(0.0, 1.0) * 0.8).max(0.1);
 
-  let final_color = if radius > 1.0 && radius < 2.5 {
-      ring_color * fragment.intensity * ring_opacity
+  if radius > SATURN_RING_INNER && radius < SATURN_RING_OUTER {
+      Shaded::opaque(ring_color * lit(fragment, uniforms) * ring_opacity)
   } else {
-      band_color * fragment.intensity
-  };
+      // The rings live in model space, so trace the sun direction there.
+      let shadow = uniforms.model_matrix.try_inverse().map_or(1.0, |inverse| {
+          let sun = inverse * Vec4::new(uniforms.sun_position.x, uniforms.sun_position.y, uniforms.sun_position.z, 1.0);
+          let sun_dir = (Vec3::new(sun.x, sun.y, sun.z) - fragment.vertex_position).normalize();
+          ring_shadow_factor(fragment.vertex_position, sun_dir, SATURN_RING_INNER, SATURN_RING_OUTER)
+      });
+      let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+      let limb = limb_darkening(&fragment.normal, &to_camera, GAS_GIANT_LIMB_DARKENING);
+      Shaded::opaque(band_color * lit(fragment, uniforms) * shadow * limb)
+  }
+}
+
+// Uranus is a nearly featureless cyan haze with faint bands near the poles.
+pub fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
+  let haze_color = Color::new(175, 230, 235);
+  let band_color = Color::new(150, 210, 220);
+
+  let latitude = fragment.noise_position.normalize().y;
+  let haze = fbm_noise(
+      uniforms.noise,
+      latitude * 8.0 + uniforms.time as f32 * 0.005,
+      0.0,
+      octaves(3, uniforms.quality),
+  );
+  let bands = (latitude * 12.0).sin() * 0.5 + 0.5;
+  let surface = haze_color.lerp(&band_color, (bands * latitude.abs() + haze * 0.2).clamp(0.0, 1.0));
+
+  let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+  let limb = limb_darkening(&fragment.normal, &to_camera, GAS_GIANT_LIMB_DARKENING);
+  Shaded::opaque(surface * lit(fragment, uniforms) * limb)
+}
+
+// Neptune is deep blue with streaky bands drifting on its winds and a dark
+// storm in the southern hemisphere.
+pub fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
+  let deep_color = Color::new(40, 70, 190);
+  let band_color = Color::new(80, 120, 225);
+  let storm_color = Color::new(20, 30, 100);
+
+  let t = uniforms.time as f32 * 0.02;
+  let position = fragment.noise_position;
+  let drift = zonal_wind(position.y) * t;
+  let streaks = domain_warp_fbm(
+      uniforms.noise,
+      (position.x + drift) * 2.0,
+      position.y * 6.0,
+      octaves(5, uniforms.quality),
+      0.8,
+  );
+  let surface = deep_color.lerp(&band_color, (streaks * 0.5 + 0.5).clamp(0.0, 1.0));
+
+  let storm_dist = ((fragment.vertex_position.x - 0.3).powi(2) + (fragment.vertex_position.y + 0.35).powi(2)).sqrt();
+  let storm = (1.0 - storm_dist * 6.0).clamp(0.0, 1.0);
+  let surface = surface.lerp(&storm_color, storm);
+
+  let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+  let limb = limb_darkening(&fragment.normal, &to_camera, GAS_GIANT_LIMB_DARKENING);
+  Shaded::opaque(surface * lit(fragment, uniforms) * limb)
+}
+
+const COMET_TRANSLUCENCY: f32 = 0.35;
 
-  final_color
+// Cheap subsurface scattering: sunlight leaking through to the side facing
+// away from the sun, strongest where that side also faces the viewer.
+fn translucency(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  let to_light = (uniforms.sun_position - fragment.world_position).normalize();
+  let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+  let back_lit = dot(&-fragment.normal, &to_light).max(0.0);
+  back_lit * dot(&fragment.normal, &to_camera).max(0.0)
 }
 
-pub fn comet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+// The nucleus is opaque; on the anti-sun side the fragment is translucent tail
+// gas whose opacity follows its brightness.
+pub fn comet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Shaded {
   let core_color = Color::new(255, 105, 180);    
   let surface_color = Color::new(72, 61, 139);   
   let crack_color = Color::new(50, 205, 50);     
@@ -363,26 +779,27 @@ pub fn comet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let pulsate = (t.sin() * 0.5 + 0.5).clamp(0.3, 1.0); 
 
   let surface_noise = fbm_noise(
-      &uniforms.noise,
-      fragment.vertex_position.x * 8.0,
-      fragment.vertex_position.y * 8.0,
-      4,  
+      uniforms.noise,
+      fragment.noise_position.x * 8.0,
+      fragment.noise_position.y * 8.0,
+      octaves(4, uniforms.quality),
   );
 
 
   let crack_noise = uniforms.noise.get_noise_2d(
-      fragment.vertex_position.x * 50.0 + t,
-      fragment.vertex_position.y * 50.0 + t,
+      fragment.noise_position.x * 50.0 + t,
+      fragment.noise_position.y * 50.0 + t,
   );
 
-  let distance = (fragment.vertex_position.x.powi(2) + fragment.vertex_position.y.powi(2)).sqrt();
+  let anti_sun = (fragment.world_position - uniforms.sun_position).normalize();
+  let tail_alignment = dot(&fragment.normal, &anti_sun).max(0.0);
 
   let tail_noise = uniforms.noise.get_noise_2d(
-      fragment.vertex_position.x * 15.0 + t,
-      fragment.vertex_position.y * 15.0 + t,
+      fragment.noise_position.x * 15.0 + t,
+      fragment.noise_position.y * 15.0 + t,
   );
   let tail_color = tail_inner_color.lerp(&tail_outer_color, tail_noise);
-  let tail_intensity = (1.0 - distance / 5.0).clamp(0.0, 1.0) * tail_noise;
+  let tail_intensity = tail_alignment * tail_noise.abs();
   let surface_effect = if crack_noise > 0.6 {
       crack_color.lerp(&surface_color, surface_noise) * (1.0 - crack_noise).clamp(0.5, 1.0)
   } else {
@@ -390,11 +807,296 @@ pub fn comet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   };
   let light_factor = 0.5 + 0.5 * fragment.vertex_position.z.clamp(-1.0, 1.0);
   let illuminated_surface = surface_effect * light_factor;
-  let final_color = if distance < 1.0 {
-      illuminated_surface * fragment.intensity  
+  if tail_alignment < 0.5 {
+      let transmitted = core_color * (translucency(fragment, uniforms) * COMET_TRANSLUCENCY);
+      Shaded::opaque(illuminated_surface * lit(fragment, uniforms) + transmitted)
   } else {
-      tail_color * tail_intensity * fragment.intensity  
-  };
+      // The tail is lit gas, so its glow feeds bloom as well as the color.
+      let glow = tail_color * tail_intensity;
+      Shaded { color: glow, emission: glow, opacity: tail_intensity.clamp(0.0, 1.0) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::create_noise;
+  use crate::texture::Texture;
+  use crate::triangle::vertex_fragment;
+
+  // A fully lit fragment at `position` on the unit sphere with `normal`
+  // standing in for the surface normal.
+  fn fragment_at(position: Vec3, normal: Vec3) -> Fragment {
+    let vertex = Vertex::new(position, normal, Vec2::zeros());
+    let mut fragment = vertex_fragment(&vertex, &Vec3::new(0.0, 0.0, 10.0));
+    fragment.intensity = 1.0;
+    fragment
+  }
+
+  #[test]
+  fn vertex_shader_keeps_the_reciprocal_of_clip_w() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    let vertex = Vertex::new(Vec3::new(0.3, 0.2, -1.0), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros());
+    let transformed = vertex_shader(&vertex, &uniforms);
+
+    // The camera sits 6 units in front of the vertex, and a perspective
+    // projection carries view distance into w.
+    assert!((transformed.clip_position.w - 6.0).abs() < 1e-5);
+    assert_eq!(transformed.w_inv, 1.0 / transformed.clip_position.w);
+  }
+
+  #[test]
+  fn gas_giants_darken_toward_the_limb() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    let position = Vec3::new(0.0, 0.6, 0.8);
+    type Shader = fn(&Fragment, &Uniforms) -> Shaded;
+    let shaders: [(&str, Shader); 4] = [
+      ("jupiter", jupiter_shader),
+      ("saturn", saturn_shader),
+      ("uranus", uranus_shader),
+      ("neptune", neptune_shader),
+    ];
+    for (name, shader) in shaders {
+      // Same point, so the same band color; only the normal differs.
+      let center = shader(&fragment_at(position, Vec3::new(0.0, 0.0, 1.0)), &uniforms).color;
+      let limb = shader(&fragment_at(position, Vec3::new(1.0, 0.0, 0.0)), &uniforms).color;
+      assert!(center.luminance() > limb.luminance(), "{}: center {:?}, limb {:?}", name, center, limb);
+    }
+  }
+
+  #[test]
+  fn degenerate_model_matrix_and_zero_normal_give_no_nan() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let model_matrix = Mat4::new_scaling(0.0);
+    let normal_matrix = create_normal_matrix(&model_matrix);
+    assert!(normal_matrix.is_none());
+
+    // `main` falls back to the identity when there is no normal matrix.
+    let uniforms = Uniforms {
+      model_matrix,
+      normal_matrix: normal_matrix.unwrap_or_else(Mat3::identity),
+      ..crate::test_uniforms(&noise, &environment, 64, 64)
+    };
+    let vertex = Vertex::new(Vec3::new(0.5, 0.5, 0.0), Vec3::zeros(), Vec2::zeros());
+    let transformed = vertex_shader(&vertex, &uniforms);
+    assert!(transformed.transformed_normal.iter().all(|c| c.is_finite()));
+    assert!(transformed.transformed_normal.magnitude() > 0.0);
+  }
+
+  #[test]
+  fn halving_the_distance_to_the_sun_quadruples_its_glow_up_to_the_clamp() {
+    let sun = Vec3::zeros();
+    let glow = |distance: f32| sun_glow_intensity(&Vec3::new(0.0, 0.0, distance), &sun);
+    assert!((glow(SUN_GLOW_REFERENCE_DISTANCE) - 1.0).abs() < 1e-6);
+    for distance in [20.0, 10.0, 5.0] {
+      let ratio = glow(distance * 0.5) / glow(distance);
+      assert!((ratio - 4.0).abs() < 1e-3, "{} at distance {}", ratio, distance);
+    }
+    assert_eq!(glow(1.0), SUN_GLOW_MAX);
+    assert_eq!(glow(0.0), SUN_GLOW_MAX);
+  }
+
+  #[test]
+  fn ambient_keeps_its_share_of_color_on_the_night_side() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = Uniforms { ambient: 0.2, ..crate::test_uniforms(&noise, &environment, 64, 64) };
+    let day = fragment_at(Vec3::new(0.0, 0.6, 0.8), Vec3::new(0.0, 0.6, 0.8));
+    let mut night = fragment_at(Vec3::new(0.0, 0.6, 0.8), Vec3::new(0.0, 0.6, 0.8));
+    night.intensity = 0.0;
+    assert_eq!(lit(&night, &uniforms), 0.2);
+    assert_eq!(lit(&day, &uniforms), 1.0);
+
+    // Uranus is its surface color scaled by `lit` and limb darkening only.
+    let channels = |shaded: Shaded| [16, 8, 0].map(|shift| ((shaded.color.to_hex() >> shift) & 0xFF) as f32);
+    let (lit_side, dark_side) = (channels(uranus_shader(&day, &uniforms)), channels(uranus_shader(&night, &uniforms)));
+    for (full, kept) in lit_side.into_iter().zip(dark_side) {
+      assert!((kept - full * 0.2).abs() <= 1.0, "{} is not 20% of {}", kept, full);
+    }
+  }
+
+  #[test]
+  fn zero_warp_domain_warp_is_plain_fbm() {
+    let noise = create_noise();
+    for (x, y) in [(0.3, 0.7), (2.5, -1.2), (10.0, 4.0)] {
+      let plain = fbm_noise(&noise, x, y, 4);
+      assert_eq!(domain_warp_fbm(&noise, x, y, 4, 0.0), plain);
+      assert_ne!(domain_warp_fbm(&noise, x, y, 4, 2.0), plain, "({x}, {y})");
+    }
+  }
 
-  final_color
+  #[test]
+  fn ring_shades_points_behind_it_from_the_sun() {
+    let sun_dir = Vec3::new(1.0, 1.0, 0.0).normalize();
+    // From (0.6, -0.6, 0) the sun ray crosses the ring plane at radius 1.2.
+    let behind = ring_shadow_factor(Vec3::new(0.6, -0.6, 0.0), sun_dir, 1.1, 2.0);
+    assert!(behind < 1.0);
+    // Above the ring the ray never crosses it; below, this one passes inside it.
+    assert_eq!(ring_shadow_factor(Vec3::new(0.6, 0.6, 0.0), sun_dir, 1.1, 2.0), 1.0);
+    assert_eq!(ring_shadow_factor(Vec3::new(0.2, -0.6, 0.0), sun_dir, 1.1, 2.0), 1.0);
+  }
+
+  #[test]
+  fn granulation_varies_across_the_surface() {
+    let noise = create_noise();
+    let samples: Vec<f32> = (0..16)
+      .map(|i| {
+        let angle = i as f32 * 0.4;
+        granulation(&noise, &Vec3::new(angle.cos(), 0.0, angle.sin()), 0)
+      })
+      .collect();
+    let (low, high) = samples.iter().fold((f32::MAX, f32::MIN), |(low, high), &s| (low.min(s), high.max(s)));
+    assert!(samples.iter().all(|s| (0.55..=1.0).contains(s)), "{samples:?}");
+    assert!(high - low > 0.1, "{samples:?}");
+  }
+
+  #[test]
+  fn sun_glint_whitens_only_ocean_at_the_mirror_angle() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    let off_angle = Uniforms { camera_position: Vec3::new(5.0, 0.0, 1.0), ..uniforms };
+    // The same biome and cloud lookups earth_shader makes at time 0 with no
+    // rotation, to find open water and cloudless land.
+    let surface_at = |i: usize| {
+      let angle = i as f32 * 0.05;
+      Vec3::new(angle.cos() * 0.6, angle.sin() * 0.6, 0.8)
+    };
+    let find = |is_wanted: &dyn Fn(f32) -> bool| (0..1000).map(surface_at).find(|p| {
+      noise.get_noise_2d(p.x * 100.0, p.y * 100.0) < 0.5 && is_wanted(noise.get_noise_2d(p.x * 50.0, p.y * 50.0))
+    }).unwrap();
+    let ocean = find(&|biome| biome < -0.2);
+    let land = find(&|biome| biome > 0.4);
+
+    // Sun and camera both sit straight out along the normal, so the sun's
+    // reflection points at the camera; moving the camera to the side breaks it.
+    let min_channel = |position: Vec3, uniforms: &Uniforms| {
+      let mut fragment = fragment_at(position, Vec3::new(0.0, 0.0, 1.0));
+      fragment.world_position = Vec3::new(0.0, 0.0, 1.0);
+      let hex = earth_shader(&fragment, uniforms).color.to_hex();
+      [16, 8, 0].map(|shift| (hex >> shift) & 0xFF).into_iter().min().unwrap()
+    };
+    assert!(min_channel(ocean, &uniforms) >= 230);
+    assert!(min_channel(ocean, &off_angle) < 150);
+    assert!(min_channel(land, &uniforms) < 150);
+  }
+
+  #[test]
+  fn earth_rotation_moves_the_continents_under_the_sun() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    let quarter_turn = Uniforms { earth_rotation: std::f32::consts::FRAC_PI_2, ..uniforms };
+    // Lighting is held fixed so only the surface lookup differs between points.
+    let color = |position: Vec3, uniforms: &Uniforms| {
+      let mut fragment = fragment_at(position, Vec3::new(0.0, 0.0, 1.0));
+      fragment.world_position = Vec3::new(0.0, 0.0, 1.0);
+      earth_shader(&fragment, uniforms).color.to_hex()
+    };
+    let clear_sky = |p: Vec3| noise.get_noise_2d(p.x * 100.0, p.y * 100.0) < 0.6;
+
+    let mut checked = 0;
+    for i in 0..20 {
+      let y = -0.6 + i as f32 * 0.06;
+      // Facing the sun at rotation 0; a quarter turn carries the same spot of
+      // ground out to the terminator at `moved`.
+      let facing = Vec3::new(0.0, y, (1.0 - y * y).sqrt());
+      let moved = Vec3::new(facing.z, y, 0.0);
+      if !clear_sky(facing) || !clear_sky(moved) {
+        continue;
+      }
+      assert_eq!(color(moved, &quarter_turn), color(facing, &uniforms), "y = {y}");
+      if color(facing, &quarter_turn) != color(facing, &uniforms) {
+        checked += 1;
+      }
+    }
+    assert!(checked >= 5, "only {checked} points showed different ground");
+  }
+
+  #[test]
+  fn aurora_glows_green_near_the_poles_at_night() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    // The sun is at +z, so a fragment facing -z is on the night side.
+    let emission = |latitude: f32| {
+      let position = Vec3::new(0.0, latitude.sin(), -latitude.cos());
+      let mut fragment = fragment_at(position, Vec3::new(0.0, 0.0, -1.0));
+      fragment.world_position = Vec3::new(0.0, 0.0, -1.0);
+      let hex = earth_shader(&fragment, &uniforms).emission.to_hex();
+      [16, 8, 0].map(|shift| (hex >> shift) & 0xFF)
+    };
+
+    // The band wavers with longitude, so sweep across it and take the peak.
+    let [red, green, blue] = (0..30)
+      .map(|i| emission((55.0 + i as f32).to_radians()))
+      .max_by_key(|channels| channels[1])
+      .unwrap();
+    assert!(green > 0 && green > red && green > blue, "{:?}", [red, green, blue]);
+    assert_eq!(emission(0.0), [0, 0, 0]);
+  }
+
+  #[test]
+  fn instance_seeds_give_asteroids_different_detail() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    let (first, second) = (Uniforms { instance_seed: 0.25, ..uniforms }, Uniforms { instance_seed: 0.75, ..uniforms });
+    let differing = (0..8)
+      .map(|i| {
+        let angle = i as f32 * 0.7;
+        Vec3::new(angle.cos() * 0.6, angle.sin() * 0.6, 0.8)
+      })
+      .filter(|&position| {
+        let fragment = fragment_at(position, position);
+        asteroid_shader(&fragment, &first).color.to_hex() != asteroid_shader(&fragment, &second).color.to_hex()
+      })
+      .count();
+    assert!(differing >= 6, "only {differing} of 8 points differ");
+  }
+
+  #[test]
+  fn comet_nucleus_passes_light_through_to_the_viewer() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    // The sun is behind and to the side of a +z facing fragment: far enough
+    // behind that Lambert gives nothing, not so far that it counts as tail.
+    let sun_position = Vec3::new(0.9165, 0.0, -0.4) * 10.0;
+    let uniforms = Uniforms { sun_position, ..crate::test_uniforms(&noise, &environment, 64, 64) };
+    let mut fragment = fragment_at(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+    fragment.world_position = Vec3::zeros();
+    fragment.intensity = 0.0;
+
+    assert!(translucency(&fragment, &uniforms) > 0.3);
+    assert!(!comet_shader(&fragment, &uniforms).color.is_black());
+    // With the viewer behind the surface, nothing leaks through to them.
+    let behind = Uniforms { camera_position: Vec3::new(0.0, 0.0, -5.0), ..uniforms };
+    assert!(comet_shader(&fragment, &behind).color.is_black());
+  }
+
+  #[test]
+  fn neighbouring_jupiter_bands_drift_in_opposite_directions() {
+    use std::f32::consts::PI;
+    // Centers of consecutive bands, where each jet blows at full speed.
+    let centers: Vec<f32> = (-2..3).map(|k| (PI / 2.0 + k as f32 * PI) / JUPITER_BAND_FREQUENCY).collect();
+    for pair in centers.windows(2) {
+      // `jupiter_shader` shifts its lookups by wind times elapsed time.
+      let (first, second) = (zonal_wind(pair[0]) * 10.0, zonal_wind(pair[1]) * 10.0);
+      assert!(first.abs() > 0.4 && second.abs() > 0.4);
+      assert!(first.signum() != second.signum(), "{pair:?}");
+    }
+  }
+
+  #[test]
+  fn moon_brightens_near_zero_phase_angle() {
+    let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+    let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+    let oblique = Uniforms { sun_position: Vec3::new(8.66, 0.0, 6.0), ..uniforms };
+    // The same fully lit fragment, so only the phase angle differs: the sun is
+    // behind the camera in one case and 60 degrees off to the side in the other.
+    let mut fragment = fragment_at(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+    fragment.world_position = Vec3::new(0.0, 0.0, 1.0);
+    let head_on = moon_shader(&fragment, &uniforms).color.luminance();
+    let sideways = moon_shader(&fragment, &oblique).color.luminance();
+    assert!(head_on > sideways * 1.2, "{head_on} vs {sideways}");
+
+    let view_dir = Vec3::new(0.0, 0.0, 1.0);
+    assert!(opposition_surge(&view_dir, &view_dir) > opposition_surge(&Vec3::new(0.866, 0.0, 0.5), &view_dir));
+  }
 }
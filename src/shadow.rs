@@ -0,0 +1,133 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::triangle::rasterize_triangle;
+use crate::vertex::Vertex;
+
+// Orthographic depth map seen from a point light looking at `target`. Depth
+// is stored as world-space distance along the light direction, so biases are
+// in scene units.
+pub struct ShadowMap {
+    size: usize,
+    depth: Vec<f32>,
+    target: Vec3,
+    light_dir: Vec3,
+    right: Vec3,
+    up: Vec3,
+    half_extent: f32,
+}
+
+impl ShadowMap {
+    // Rasterizes `vertex_array` (model space, three vertices per triangle)
+    // into a `size` x `size` map covering a sphere of `radius` around
+    // `target`.
+    pub fn render(
+        vertex_array: &[Vertex],
+        model_matrix: &Mat4,
+        light_position: Vec3,
+        target: Vec3,
+        radius: f32,
+        size: usize,
+    ) -> Self {
+        let light_dir = (target - light_position).normalize();
+        let reference_up = if light_dir.y.abs() > 0.99 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+        let right = light_dir.cross(&reference_up).normalize();
+        let up = right.cross(&light_dir);
+
+        let mut map = ShadowMap {
+            size,
+            depth: vec![f32::INFINITY; size * size],
+            target,
+            light_dir,
+            right,
+            up,
+            half_extent: radius.max(f32::EPSILON),
+        };
+
+        let projected: Vec<Vec3> = vertex_array
+            .iter()
+            .map(|vertex| {
+                let world = model_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+                map.project(&Vec3::new(world.x, world.y, world.z))
+            })
+            .collect();
+
+        for tri in projected.chunks_exact(3) {
+            for sample in rasterize_triangle(&tri[0], &tri[1], &tri[2], size, size) {
+                let index = sample.y * size + sample.x;
+                if sample.depth < map.depth[index] {
+                    map.depth[index] = sample.depth;
+                }
+            }
+        }
+
+        map
+    }
+
+    // Map pixel coordinates in x and y, depth along the light in z.
+    fn project(&self, world_position: &Vec3) -> Vec3 {
+        let offset = world_position - self.target;
+        let scale = self.size as f32 / (2.0 * self.half_extent);
+        Vec3::new(
+            (offset.dot(&self.right) + self.half_extent) * scale,
+            (self.half_extent - offset.dot(&self.up)) * scale,
+            offset.dot(&self.light_dir),
+        )
+    }
+
+    // Fraction of light reaching `world_position`, averaged over a
+    // `kernel` x `kernel` block of map texels (percentage-closer filtering).
+    // A kernel of 1 gives hard 0/1 shadows. The block is centered on the
+    // point's texel, so even kernels round up to the next odd size. Points
+    // outside the map are lit.
+    pub fn shadow_factor(&self, world_position: &Vec3, bias: f32, kernel: usize) -> f32 {
+        let p = self.project(world_position);
+        let half = (kernel.max(1) / 2) as i64;
+        let cx = p.x.floor() as i64;
+        let cy = p.y.floor() as i64;
+
+        let mut lit = 0;
+        let mut total = 0;
+        for dy in -half..=half {
+            for dx in -half..=half {
+                let (x, y) = (cx + dx, cy + dy);
+                total += 1;
+                let outside = x < 0 || y < 0 || x >= self.size as i64 || y >= self.size as i64;
+                if outside || p.z - bias <= self.depth[y as usize * self.size + x as usize] {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f32 / total as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    // A light straight above the origin and a square occluder one unit up
+    // over the -x half of the map.
+    fn half_shadowed_map() -> ShadowMap {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let corners = [(-2.0, -2.0), (0.0, -2.0), (0.0, 2.0), (-2.0, -2.0), (0.0, 2.0), (-2.0, 2.0)];
+        let occluder: Vec<Vertex> = corners.iter()
+            .map(|&(x, z)| Vertex::new(Vec3::new(x, 1.0, z), normal, Vec2::zeros()))
+            .collect();
+        ShadowMap::render(&occluder, &Mat4::identity(), Vec3::new(0.0, 10.0, 0.0), Vec3::zeros(), 2.0, 16)
+    }
+
+    #[test]
+    fn boundary_texels_are_hard_with_kernel_one_and_soft_with_kernel_three() {
+        let map = half_shadowed_map();
+        let shadowed = Vec3::new(-0.1, 0.0, 0.0);
+        let lit = Vec3::new(0.1, 0.0, 0.0);
+
+        assert_eq!(map.shadow_factor(&shadowed, 0.05, 1), 0.0);
+        assert_eq!(map.shadow_factor(&lit, 0.05, 1), 1.0);
+        for point in [shadowed, lit] {
+            let soft = map.shadow_factor(&point, 0.05, 3);
+            assert!(soft > 0.0 && soft < 1.0, "kernel 3 at {:?} gave {}", point, soft);
+            assert_eq!(map.shadow_factor(&point, 0.05, 2), soft);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use std::f32::consts::PI;
+use crate::{create_perspective_matrix, create_view_matrix, create_viewport_matrix};
+
+// Sprite radius at full brightness in the low-resolution environment map,
+// small enough that stars stay single points there.
+const ENVIRONMENT_STAR_RADIUS: f32 = 0.5;
+
+pub struct Star {
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub magnitude: f32,
+}
+
+impl Star {
+    // Unit direction with azimuth measured from +X toward +Z and elevation
+    // toward +Y, the same convention as the panorama view.
+    pub fn direction(&self) -> Vec3 {
+        let azimuth = self.azimuth.to_radians();
+        let elevation = self.elevation.to_radians();
+        Vec3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            elevation.cos() * azimuth.sin(),
+        )
+    }
+
+    // Maps visual magnitude to display brightness; magnitude 0 is full white
+    // and each step is dimmer, with a floor so faint stars stay visible.
+    pub fn brightness(&self) -> f32 {
+        10f32.powf(-0.4 * self.magnitude).powf(0.4).clamp(0.1, 1.0)
+    }
+
+    // Gray level matching the brightness, as 0x00RRGGBB.
+    pub fn color(&self) -> u32 {
+        let level = (self.brightness() * 255.0) as u32;
+        (level << 16) | (level << 8) | level
+    }
+}
+
+// Reads `azimuth,elevation,magnitude` rows in degrees. Blank lines, `#`
+// comments and a header row are skipped.
+pub fn load_star_catalog(path: &str) -> Result<Vec<Star>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path, err))?;
+
+    let mut stars = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("azimuth") {
+            continue;
+        }
+
+        let fields: Vec<f32> = line.split(',')
+            .map(|field| field.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|err| format!("{}:{}: {}", path, line_number + 1, err))?;
+        if fields.len() != 3 {
+            return Err(format!("{}:{}: expected 3 columns, found {}", path, line_number + 1, fields.len()));
+        }
+
+        stars.push(Star { azimuth: fields[0], elevation: fields[1], magnitude: fields[2] });
+    }
+
+    Ok(stars)
+}
+
+// Plots each star as a small soft sprite sized by its brightness. Stars sit at infinity, so
+// only the camera's orientation matters, and they are written at infinite
+// depth: they only land on background, any geometry drawn afterwards covers
+// them, and depth-based passes still see those pixels as background.
+pub fn render_stars(framebuffer: &mut Framebuffer, camera: &Camera, stars: &[Star]) {
+    let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+    let projection_matrix = create_perspective_matrix(camera.fov, framebuffer.width as f32, framebuffer.height as f32);
+    render_stars_through(framebuffer, &view_matrix, &projection_matrix, stars);
+}
+
+// `render_stars` with explicit matrices, for views like poster tiles whose
+// projection isn't the camera's own.
+pub fn render_stars_through(framebuffer: &mut Framebuffer, view_matrix: &Mat4, projection_matrix: &Mat4, stars: &[Star]) {
+    let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+
+    for star in stars {
+        let direction = star.direction();
+        let view = view_matrix * Vec4::new(direction.x, direction.y, direction.z, 0.0);
+        let clip = projection_matrix * Vec4::new(view.x, view.y, view.z, 1.0);
+        if clip.w <= 0.0 {
+            continue;
+        }
+
+        let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, 0.0, 1.0);
+        if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+            continue;
+        }
+        let screen = viewport_matrix * ndc;
+
+        framebuffer.draw_sprite(screen.x, screen.y, star.brightness() * 1.5, star.color(), f32::INFINITY);
+    }
+}
+
+// Plots the whole sky into an equirectangular framebuffer, with the mapping
+// environment lookups use: u = 0.5 looks down +X, v = 0 is straight up.
+pub fn render_star_environment(framebuffer: &mut Framebuffer, stars: &[Star]) {
+    for star in stars {
+        let direction = star.direction();
+        let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        let x = u * framebuffer.width as f32;
+        let y = v * framebuffer.height as f32;
+        framebuffer.draw_sprite(x, y, star.brightness() * ENVIRONMENT_STAR_RADIUS, star.color(), f32::INFINITY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::DEFAULT_FOV;
+
+    // Brightness-weighted center of everything drawn, in pixel coordinates.
+    fn centroid(framebuffer: &Framebuffer) -> (f32, f32) {
+        let (mut x_sum, mut y_sum, mut total) = (0.0, 0.0, 0.0);
+        for (index, &pixel) in framebuffer.buffer.iter().enumerate() {
+            let weight = (pixel & 0xFF) as f32;
+            x_sum += weight * ((index % framebuffer.width) as f32 + 0.5);
+            y_sum += weight * ((index / framebuffer.width) as f32 + 0.5);
+            total += weight;
+        }
+        assert!(total > 0.0, "nothing was drawn");
+        (x_sum / total, y_sum / total)
+    }
+
+    #[test]
+    fn stars_project_to_their_expected_pixels() {
+        // Looking down +X with +Y up, so azimuth turns right and elevation up.
+        let camera = Camera::new(Vec3::zeros(), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let offset = (0.4 * (DEFAULT_FOV / 2.0).tan()).atan().to_degrees();
+        let cases = [
+            (Star { azimuth: 0.0, elevation: 0.0, magnitude: 6.0 }, (50.5, 50.5)),
+            (Star { azimuth: offset, elevation: 0.0, magnitude: 6.0 }, (50.5 + 0.4 * 50.5, 50.5)),
+            (Star { azimuth: 0.0, elevation: offset, magnitude: 6.0 }, (50.5, 50.5 - 0.4 * 50.5)),
+        ];
+        for (star, (expected_x, expected_y)) in cases {
+            let mut framebuffer = Framebuffer::new(101, 101);
+            render_stars(&mut framebuffer, &camera, &[star]);
+            let (x, y) = centroid(&framebuffer);
+            assert!((x - expected_x).abs() < 0.5 && (y - expected_y).abs() < 0.5, "({x}, {y})");
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::color::Color;
+use std::fs::File;
+
+// Default color of the "missing texture" checkerboard, alternating with black.
+pub const MISSING_TEXTURE_COLOR: u32 = 0xFF00FF;
+const MISSING_TEXTURE_SIZE: usize = 64;
+const MISSING_TEXTURE_CELL: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+impl Filter {
+    pub fn next(self) -> Self {
+        match self {
+            Filter::Nearest => Filter::Bilinear,
+            Filter::Bilinear => Filter::Nearest,
+        }
+    }
+}
+
+// How coordinates outside [0, 1] map back onto the texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wrap {
+    Repeat,
+    #[default]
+    Clamp,
+    Mirror,
+}
+
+impl Wrap {
+    pub fn next(self) -> Self {
+        match self {
+            Wrap::Repeat => Wrap::Clamp,
+            Wrap::Clamp => Wrap::Mirror,
+            Wrap::Mirror => Wrap::Repeat,
+        }
+    }
+
+    // Maps a possibly out-of-range texel index into 0..size.
+    fn index(self, i: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            Wrap::Repeat => i.rem_euclid(size),
+            Wrap::Clamp => i.clamp(0, size - 1),
+            Wrap::Mirror => {
+                let period = i.rem_euclid(2 * size);
+                if period < size { period } else { 2 * size - 1 - period }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+impl MipLevel {
+    // Half-size level averaging each 2x2 block; odd edges reuse the last texel.
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0) = ((x * 2).min(self.width - 1), (y * 2).min(self.height - 1));
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let top = self.texel(x0, y0).lerp(&self.texel(x1, y0), 0.5);
+                let bottom = self.texel(x0, y1).lerp(&self.texel(x1, y1), 0.5);
+                pixels.push(top.lerp(&bottom, 0.5).to_hex());
+            }
+        }
+        MipLevel { width, height, pixels }
+    }
+
+    fn sample(&self, u: f32, v: f32, filter: Filter, wrap: Wrap) -> Color {
+        let (x, y) = (u * self.width as f32, v * self.height as f32);
+        match filter {
+            Filter::Nearest => {
+                let x = wrap.index(x.floor() as i64, self.width);
+                let y = wrap.index(y.floor() as i64, self.height);
+                self.texel(x, y)
+            }
+            Filter::Bilinear => {
+                // Texel centers sit at half-integer coordinates, so shift by
+                // half a texel before splitting into cell and fraction.
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (cx, cy) = (x.floor() as i64, y.floor() as i64);
+                let (x0, y0) = (wrap.index(cx, self.width), wrap.index(cy, self.height));
+                let (x1, y1) = (wrap.index(cx + 1, self.width), wrap.index(cy + 1, self.height));
+                let (fx, fy) = (x - x.floor(), y - y.floor());
+
+                let top = self.texel(x0, y0).lerp(&self.texel(x1, y0), fx);
+                let bottom = self.texel(x0, y1).lerp(&self.texel(x1, y1), fx);
+                top.lerp(&bottom, fy)
+            }
+        }
+    }
+
+    fn texel(&self, x: usize, y: usize) -> Color {
+        Color::from_hex(self.pixels[y * self.width + x])
+    }
+}
+
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    // Mip chain from full size down to 1x1; level 0 is the original image.
+    levels: Vec<MipLevel>,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, pixels: Vec<u32>) -> Self {
+        assert_eq!(pixels.len(), width * height, "texture size does not match pixel count");
+        let mut levels = vec![MipLevel { width, height, pixels }];
+        while let Some(last) = levels.last().filter(|level| level.width * level.height > 1) {
+            let next = last.downsample();
+            levels.push(next);
+        }
+        Texture { width, height, levels }
+    }
+
+    // Loads an 8-bit PNG. Any failure is logged with the file name and
+    // replaced by a checkerboard of `missing_color` and black, so a broken
+    // reference is obvious on screen instead of crashing or rendering black.
+    pub fn from_png(path: &str, missing_color: u32) -> Self {
+        match Self::load_png(path) {
+            Ok(texture) => texture,
+            Err(err) => {
+                eprintln!("warning: {}, using missing-texture checkerboard", err);
+                Self::checkerboard(MISSING_TEXTURE_SIZE, MISSING_TEXTURE_CELL, missing_color, 0x000000)
+            }
+        }
+    }
+
+    fn load_png(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| format!("failed to open texture {}: {}", path, err))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .map_err(|err| format!("failed to decode texture {}: {}", path, err))?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut data)
+            .map_err(|err| format!("failed to decode texture {}: {}", path, err))?;
+
+        let channels = info.color_type.samples();
+        let pixels = data[..info.buffer_size()]
+            .chunks_exact(channels)
+            .map(|texel| match channels {
+                1 | 2 => u32::from(texel[0]) * 0x010101,
+                _ => (u32::from(texel[0]) << 16) | (u32::from(texel[1]) << 8) | u32::from(texel[2]),
+            })
+            .collect();
+
+        Ok(Texture::new(info.width as usize, info.height as usize, pixels))
+    }
+
+    pub fn checkerboard(size: usize, cell: usize, a: u32, b: u32) -> Self {
+        let cell = cell.max(1);
+        let pixels = (0..size * size)
+            .map(|i| if ((i % size) / cell + (i / size) / cell).is_multiple_of(2) { a } else { b })
+            .collect();
+        Texture::new(size, size, pixels)
+    }
+
+    // Texture lookup with (0, 0) at the top-left and v increasing downward,
+    // the same convention Obj uses for tex_coords. `wrap` decides how
+    // coordinates outside [0, 1] are folded back. `lod` picks the mip level,
+    // 0 being full size; fractional values blend the two nearest levels.
+    pub fn sample(&self, u: f32, v: f32, filter: Filter, wrap: Wrap, lod: f32) -> Color {
+        if self.width == 0 || self.height == 0 {
+            return Color::black();
+        }
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as f32);
+        let fine = lod.floor() as usize;
+        let color = self.levels[fine].sample(u, v, filter, wrap);
+        if lod.fract() > 0.0 {
+            color.lerp(&self.levels[fine + 1].sample(u, v, filter, wrap), lod.fract())
+        } else {
+            color
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_modes_fold_coordinates_past_the_edge() {
+        let texture = Texture::new(4, 1, vec![0x000000, 0x404040, 0x808080, 0xC0C0C0]);
+        let sample = |u: f32, filter: Filter, wrap: Wrap| texture.sample(u, 0.5, filter, wrap, 0.0).to_hex();
+
+        for filter in [Filter::Nearest, Filter::Bilinear] {
+            assert_eq!(sample(1.5, filter, Wrap::Repeat), sample(0.5, filter, Wrap::Repeat));
+        }
+        assert_eq!(sample(1.375, Filter::Nearest, Wrap::Repeat), 0x404040);
+        assert_eq!(sample(1.375, Filter::Nearest, Wrap::Clamp), 0xC0C0C0);
+        assert_eq!(sample(-0.5, Filter::Nearest, Wrap::Clamp), 0x000000);
+        assert_eq!(sample(1.375, Filter::Nearest, Wrap::Mirror), sample(0.625, Filter::Nearest, Wrap::Mirror));
+        assert_eq!(sample(-0.125, Filter::Nearest, Wrap::Mirror), 0x000000);
+    }
+
+    #[test]
+    fn center_of_a_2x2_texture_blends_all_four_texels_under_bilinear() {
+        let texture = Texture::new(2, 2, vec![0xC80000, 0x00C800, 0x0000C8, 0xC8C8C8]);
+        assert_eq!(texture.sample(0.5, 0.5, Filter::Bilinear, Wrap::Clamp, 0.0).to_hex(), 0x646464);
+        assert_eq!(texture.sample(0.5, 0.5, Filter::Nearest, Wrap::Clamp, 0.0).to_hex(), 0xC8C8C8);
+    }
+
+    #[test]
+    fn missing_png_falls_back_to_the_checkerboard() {
+        let path = std::env::temp_dir().join(format!("lab4_missing_{}.png", std::process::id()));
+        let texture = Texture::from_png(&path.to_string_lossy(), MISSING_TEXTURE_COLOR);
+        assert_eq!((texture.width, texture.height), (MISSING_TEXTURE_SIZE, MISSING_TEXTURE_SIZE));
+        let level = &texture.levels[0];
+        assert_eq!(level.texel(0, 0).to_hex(), MISSING_TEXTURE_COLOR);
+        assert_eq!(level.texel(MISSING_TEXTURE_CELL, 0).to_hex(), 0x000000);
+        assert_eq!(level.texel(MISSING_TEXTURE_CELL, MISSING_TEXTURE_CELL).to_hex(), MISSING_TEXTURE_COLOR);
+    }
+
+    #[test]
+    fn positive_lod_bias_samples_a_coarser_level() {
+        let texture = Texture::new(2, 2, vec![0xC80000, 0x00C800, 0x0000C8, 0xC8C8C8]);
+        let sample = |bias: f32| texture.sample(0.25, 0.25, Filter::Nearest, Wrap::Clamp, bias).to_hex();
+        assert_eq!(sample(0.0), 0xC80000);
+        assert_eq!(sample(1.0), texture.levels[1].texel(0, 0).to_hex());
+        assert_eq!(sample(1.0), 0x646464);
+        assert_eq!(sample(-1.0), sample(0.0));
+    }
+}
@@ -0,0 +1,90 @@
+// Animation clock with pause and single-frame stepping. Time advances by one
+// unit per `tick` while running; while paused it only advances on the tick
+// right after `step`, then holds again. After a seek the next tick holds, so
+// the frame that follows shows exactly the requested time.
+pub struct TimeControl {
+    time: u32,
+    paused: bool,
+    step_once: bool,
+    seeked: bool,
+}
+
+impl TimeControl {
+    pub fn new() -> Self {
+        TimeControl {
+            time: 0,
+            paused: false,
+            step_once: false,
+            seeked: false,
+        }
+    }
+
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step_once = false;
+    }
+
+    // Requests a single time step on the next tick. Only meaningful while
+    // paused, since a running clock advances anyway.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.step_once = true;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.time = 0;
+    }
+
+    pub fn set_time(&mut self, time: u32) {
+        self.time = time;
+        self.seeked = true;
+    }
+
+    // Jumps by `delta` frames in either direction, stopping at 0.
+    pub fn seek(&mut self, delta: i64) {
+        let time = (self.time as i64 + delta).clamp(0, u32::MAX as i64);
+        self.set_time(time as u32);
+    }
+
+    pub fn tick(&mut self) {
+        if self.seeked {
+            self.seeked = false;
+        } else if !self.paused || self.step_once {
+            self.time += 1;
+        }
+        self.step_once = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_while_paused_advances_exactly_one_frame() {
+        let mut clock = TimeControl::new();
+        for _ in 0..5 {
+            clock.tick();
+        }
+        clock.toggle_pause();
+        clock.tick();
+        assert_eq!(clock.time(), 5);
+
+        clock.step();
+        clock.tick();
+        assert_eq!(clock.time(), 6);
+        for _ in 0..3 {
+            clock.tick();
+        }
+        assert_eq!(clock.time(), 6);
+
+        clock.toggle_pause();
+        clock.tick();
+        assert_eq!(clock.time(), 7);
+    }
+}
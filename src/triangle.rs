@@ -1,56 +1,289 @@
-use nalgebra_glm::{Vec3, dot};
+use nalgebra_glm::{Vec2, Vec3, dot};
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::color::Color;
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-  let mut fragments = Vec::new();
-  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+// One covered pixel of a screen-space triangle: the integer pixel, the
+// barycentric weights of its center with respect to (v0, v1, v2), and the
+// interpolated depth.
+pub struct CoverageSample {
+  pub x: usize,
+  pub y: usize,
+  pub weights: (f32, f32, f32),
+  pub depth: f32,
+}
+
+// How render() turns clipped triangles into pixels: shaded surfaces, their
+// edges only, or just their vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayMode {
+  #[default]
+  Solid,
+  Wireframe,
+  Point,
+}
+
+impl DisplayMode {
+  pub fn next(self) -> Self {
+    match self {
+      DisplayMode::Solid => DisplayMode::Wireframe,
+      DisplayMode::Wireframe => DisplayMode::Point,
+      DisplayMode::Point => DisplayMode::Solid,
+    }
+  }
+}
+
+// Where lighting normals come from: the mesh's interpolated vertex normals,
+// or each triangle's face normal for meshes whose `vn` data can't be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalSource {
+  #[default]
+  Vertex,
+  Geometric,
+}
+
+impl NormalSource {
+  pub fn next(self) -> Self {
+    match self {
+      NormalSource::Vertex => NormalSource::Geometric,
+      NormalSource::Geometric => NormalSource::Vertex,
+    }
+  }
+}
+
+// World-space face normal of a counter-clockwise triangle, or +Y when the
+// triangle is degenerate.
+pub fn geometric_normal(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec3 {
+  let normal = (v2.world_position - v1.world_position).cross(&(v3.world_position - v1.world_position));
+  if normal.magnitude_squared() > f32::EPSILON * f32::EPSILON {
+    normal.normalize()
+  } else {
+    Vec3::new(0.0, 1.0, 0.0)
+  }
+}
 
-  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+// Coverage is tested at each pixel center against the unrounded screen-space
+// vertex positions, so sub-pixel vertex motion moves edges smoothly instead of
+// snapping to whole pixels. Inputs are screen-space positions (x, y in pixels,
+// z as depth); pixels outside `width` x `height` are never produced, and
+// zero-area triangles produce nothing.
+pub fn rasterize_triangle(v0: &Vec3, v1: &Vec3, v2: &Vec3, width: usize, height: usize) -> Vec<CoverageSample> {
+  let mut samples = Vec::new();
+  if width == 0 || height == 0 {
+    return samples;
+  }
 
-  let light_dir = Vec3::new(0.0, 0.0, 1.0);
+  let Some((triangle_area, [a, b, c], flipped)) = canonical_winding(v0, v1, v2) else {
+    return samples;
+  };
 
-  let triangle_area = edge_function(&a, &b, &c);
+  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(v0, v1, v2);
+  let min_x = min_x.max(0);
+  let min_y = min_y.max(0);
+  let max_x = max_x.min(width as i32 - 1);
+  let max_y = max_y.min(height as i32 - 1);
 
   for y in min_y..=max_y {
     for x in min_x..=max_x {
       let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
 
-      let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+      // Depth is summed in canonical order so both windings round alike.
+      let canonical = barycentric_coordinates(&point, a, b, c, triangle_area);
+      let weights = unflip(canonical, flipped);
+      let (w1, w2, w3) = weights;
+
+      if (0.0..=1.0).contains(&w1) &&
+         (0.0..=1.0).contains(&w2) &&
+         (0.0..=1.0).contains(&w3) {
+        samples.push(CoverageSample {
+          x: x as usize,
+          y: y as usize,
+          weights: narrow_weights(weights),
+          depth: interpolate_depth(a, b, c, canonical),
+        });
+      }
+    }
+  }
+
+  samples
+}
+
+// Pixels along the segment from `a` to `b` (screen space, z as depth), one
+// per step along the major axis, with depth interpolated linearly. The
+// weights are (1 - t, t, 0).
+pub fn rasterize_line(a: &Vec3, b: &Vec3, width: usize, height: usize) -> Vec<CoverageSample> {
+  let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil().max(1.0);
+  if !steps.is_finite() {
+    return Vec::new();
+  }
+
+  (0..=steps as usize).filter_map(|i| {
+    let t = i as f32 / steps;
+    let x = a.x + (b.x - a.x) * t;
+    let y = a.y + (b.y - a.y) * t;
+    if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+      return None;
+    }
+    Some(CoverageSample {
+      x: x as usize,
+      y: y as usize,
+      weights: (1.0 - t, t, 0.0),
+      depth: a.z + (b.z - a.z) * t,
+    })
+  }).collect()
+}
+
+pub const MAX_SAMPLES: usize = 8;
 
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
+// Which of a pixel's subsamples a triangle covers (bit i for sample i) and
+// the triangle's depth at each of them.
+pub struct SampleCoverage {
+  pub mask: u32,
+  pub depths: [f32; MAX_SAMPLES],
+}
 
-        let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-        let normal = normal.normalize();
+// Like rasterize_triangle, but coverage and depth are evaluated at each of
+// `offsets` (fractions of a pixel from its top-left corner) instead of the
+// pixel center. A pixel is produced when any sample is covered; its shading
+// weights and depth come from the first covered sample, so attributes are
+// never extrapolated past the triangle's edge.
+pub fn rasterize_triangle_multisample(
+  v0: &Vec3,
+  v1: &Vec3,
+  v2: &Vec3,
+  width: usize,
+  height: usize,
+  offsets: &[(f32, f32)],
+) -> Vec<(CoverageSample, SampleCoverage)> {
+  let mut samples = Vec::new();
+  if width == 0 || height == 0 {
+    return samples;
+  }
 
-        let intensity = dot(&normal, &light_dir).max(0.0);
+  let Some((triangle_area, [a, b, c], flipped)) = canonical_winding(v0, v1, v2) else {
+    return samples;
+  };
 
-        let base_color = Color::new(100, 100, 100);
-        let lit_color = base_color * intensity;
+  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(v0, v1, v2);
+  let min_x = min_x.max(0);
+  let min_y = min_y.max(0);
+  let max_x = max_x.min(width as i32 - 1);
+  let max_y = max_y.min(height as i32 - 1);
 
-        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+  for y in min_y..=max_y {
+    for x in min_x..=max_x {
+      let mut coverage = SampleCoverage { mask: 0, depths: [f32::INFINITY; MAX_SAMPLES] };
+      let mut shading = None;
 
-        let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+      for (i, &(ox, oy)) in offsets.iter().take(MAX_SAMPLES).enumerate() {
+        let point = Vec3::new(x as f32 + ox, y as f32 + oy, 0.0);
+        let canonical = barycentric_coordinates(&point, a, b, c, triangle_area);
+        let weights = unflip(canonical, flipped);
+        let (w1, w2, w3) = weights;
+        if (0.0..=1.0).contains(&w1) && (0.0..=1.0).contains(&w2) && (0.0..=1.0).contains(&w3) {
+          let depth = interpolate_depth(a, b, c, canonical);
+          coverage.mask |= 1 << i;
+          coverage.depths[i] = depth;
+          shading.get_or_insert((narrow_weights(weights), depth));
+        }
+      }
 
-        fragments.push(
-            Fragment::new(
-                x as f32,
-                y as f32,
-                lit_color,
-                depth,
-                normal,
-                intensity,
-                vertex_position,
-            )
-        );
+      if let Some((weights, depth)) = shading {
+        samples.push((CoverageSample { x: x as usize, y: y as usize, weights, depth }, coverage));
       }
     }
   }
 
-  fragments
+  samples
+}
+
+// Fragments shaded once per pixel, each carrying its per-sample coverage.
+pub fn triangle_multisample(
+  v1: &Vertex,
+  v2: &Vertex,
+  v3: &Vertex,
+  light_position: &Vec3,
+  width: usize,
+  height: usize,
+  offsets: &[(f32, f32)],
+) -> Vec<(Fragment, SampleCoverage)> {
+  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+
+  rasterize_triangle_multisample(&a, &b, &c, width, height, offsets).into_iter().map(|(sample, coverage)| {
+    let position = Vec2::new(sample.x as f32, sample.y as f32);
+    (interpolate_fragment(v1, v2, v3, sample.weights, position, sample.depth, light_position), coverage)
+  }).collect()
+}
+
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, light_position: &Vec3, width: usize, height: usize) -> Vec<Fragment> {
+  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+
+  rasterize_triangle(&a, &b, &c, width, height).into_iter().map(|sample| {
+    let position = Vec2::new(sample.x as f32, sample.y as f32);
+    interpolate_fragment(v1, v2, v3, sample.weights, position, sample.depth, light_position)
+  }).collect()
+}
+
+// A fragment located exactly at `vertex`, used to run fragment shaders once
+// per vertex for Gouraud shading.
+pub fn vertex_fragment(vertex: &Vertex, light_position: &Vec3) -> Fragment {
+  let position = Vec2::new(vertex.transformed_position.x, vertex.transformed_position.y);
+  interpolate_fragment(vertex, vertex, vertex, (1.0, 0.0, 0.0), position, vertex.transformed_position.z, light_position)
+}
+
+fn interpolate_fragment(
+  v1: &Vertex,
+  v2: &Vertex,
+  v3: &Vertex,
+  (w1, w2, w3): (f32, f32, f32),
+  position: Vec2,
+  depth: f32,
+  light_position: &Vec3,
+) -> Fragment {
+  // Screen-space weights are affine in screen space; reweighting by 1/w makes
+  // surface attributes follow the surface under perspective. Depth stays
+  // affine, since NDC z is itself linear in screen space, and so does
+  // velocity, which is a screen-space quantity.
+  let (p1, p2, p3) = (w1 * v1.w_inv, w2 * v2.w_inv, w3 * v3.w_inv);
+  let p_sum = p1 + p2 + p3;
+  let (p1, p2, p3) = if p_sum.abs() > f32::EPSILON {
+    (p1 / p_sum, p2 / p_sum, p3 / p_sum)
+  } else {
+    (w1, w2, w3)
+  };
+
+  let normal = v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3;
+  let normal = normal.normalize();
+
+  let world_position = v1.world_position * p1 + v2.world_position * p2 + v3.world_position * p3;
+  let light_dir = (light_position - world_position).normalize();
+
+  let intensity = dot(&normal, &light_dir).max(0.0);
+
+  let vertex_position = v1.position * p1 + v2.position * p2 + v3.position * p3;
+
+  Fragment {
+    position,
+    depth,
+    normal,
+    intensity,
+    vertex_position,
+    noise_position: vertex_position,
+    world_position,
+    ao: v1.ao * p1 + v2.ao * p2 + v3.ao * p3,
+    roughness: v1.roughness * p1 + v2.roughness * p2 + v3.roughness * p3,
+    vertex_color: v1.color * p1 + v2.color * p2 + v3.color * p3,
+    tex_coords: v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3,
+    velocity: v1.velocity * w1 + v2.velocity * w2 + v3.velocity * w3,
+    shaded_color: v1.shaded_color * p1 + v2.shaded_color * p2 + v3.shaded_color * p3,
+    shaded_emission: v1.shaded_emission * p1 + v2.shaded_emission * p2 + v3.shaded_emission * p3,
+    shaded_opacity: v1.shaded_opacity * p1 + v2.shaded_opacity * p2 + v3.shaded_opacity * p3,
+    material_id: v1.material_id,
+  }
+}
+
+// Unsigned screen-space area in square pixels.
+pub fn screen_area(v0: &Vec3, v1: &Vec3, v2: &Vec3) -> f32 {
+    narrow(edge_function(v0, v1, v2).abs() * 0.5)
 }
 
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
@@ -62,7 +295,37 @@ fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i3
     (min_x, min_y, max_x, max_y)
 }
 
-fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
+// Precision of the rasterizer's edge functions and depth interpolation.
+// Attributes are still stored as f32.
+#[cfg(feature = "f64-interpolation")]
+type Real = f64;
+#[cfg(not(feature = "f64-interpolation"))]
+type Real = f32;
+
+// Orders a triangle so its signed area is positive by swapping the last two
+// vertices of the other winding, and returns that (absolute) area, the
+// reordered vertices and whether they were swapped. Both windings of a face
+// then evaluate exactly the same edge functions, so every pixel gets the same
+// coverage and the same weights per vertex; whether a winding is drawn at all
+// is left to culling. None for degenerate triangles.
+fn canonical_winding<'a>(v0: &'a Vec3, v1: &'a Vec3, v2: &'a Vec3) -> Option<(Real, [&'a Vec3; 3], bool)> {
+    let area = edge_function(v0, v1, v2);
+    if area.abs() < 1e-6 || !area.is_finite() {
+        return None;
+    }
+    if area > 0.0 {
+        Some((area, [v0, v1, v2], false))
+    } else {
+        Some((-area, [v0, v2, v1], true))
+    }
+}
+
+// Maps weights computed on canonical_winding's order back to the caller's.
+fn unflip((w1, w2, w3): (Real, Real, Real), flipped: bool) -> (Real, Real, Real) {
+    if flipped { (w1, w3, w2) } else { (w1, w2, w3) }
+}
+
+fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: Real) -> (Real, Real, Real) {
     let w1 = edge_function(b, c, p) / area;
     let w2 = edge_function(c, a, p) / area;
     let w3 = edge_function(a, b, p) / area;
@@ -70,8 +333,163 @@ fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) ->
     (w1, w2, w3)
 }
 
-fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
-    (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
+fn interpolate_depth(a: &Vec3, b: &Vec3, c: &Vec3, (w1, w2, w3): (Real, Real, Real)) -> f32 {
+    narrow(a.z as Real * w1 + b.z as Real * w2 + c.z as Real * w3)
+}
+
+fn narrow_weights((w1, w2, w3): (Real, Real, Real)) -> (f32, f32, f32) {
+    (narrow(w1), narrow(w2), narrow(w3))
+}
+
+// A no-op unless f64 interpolation is enabled.
+#[allow(clippy::unnecessary_cast)]
+fn narrow(value: Real) -> f32 {
+    value as f32
+}
+
+fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> Real {
+    let (ax, ay) = (a.x as Real, a.y as Real);
+    (c.x as Real - ax) * (b.y as Real - ay) - (c.y as Real - ay) * (b.x as Real - ax)
 }
 
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::{HashMap, HashSet};
 
+  #[test]
+  fn slow_rotation_changes_coverage_a_few_edge_pixels_at_a_time() {
+    let center = Vec3::new(32.0, 32.0, 0.5);
+    let corners = [Vec3::new(20.0, 0.0, 0.0), Vec3::new(-12.0, 16.0, 0.0), Vec3::new(-8.0, -18.0, 0.0)];
+    let covered = |angle: f32| {
+      let (sin, cos) = angle.sin_cos();
+      let [a, b, c] = corners.map(|p| center + Vec3::new(p.x * cos - p.y * sin, p.x * sin + p.y * cos, 0.0));
+      rasterize_triangle(&a, &b, &c, 64, 64).into_iter().map(|sample| (sample.x, sample.y)).collect::<HashSet<_>>()
+    };
+
+    let mut previous = covered(0.0);
+    let mut changed = 0;
+    for frame in 1..=10 {
+      let current = covered(frame as f32 * 0.2f32.to_radians());
+      let flipped = previous.symmetric_difference(&current).count();
+      // A fifth of a degree moves no corner more than 0.07 px, so only a few
+      // pixels along the 100-pixel perimeter can flip, never whole rows of
+      // the 500-pixel interior.
+      assert!(flipped <= 12, "frame {}: {} pixels changed", frame, flipped);
+      changed += flipped;
+      previous = current;
+    }
+    assert!(changed > 0, "sub-pixel motion never moved an edge");
+  }
+
+  #[test]
+  fn both_windings_rasterize_identically() {
+    let (a, b, c) = (Vec3::new(2.0, 1.0, 0.2), Vec3::new(17.0, 4.0, 0.5), Vec3::new(6.0, 15.0, 0.9));
+    let by_pixel = |samples: Vec<CoverageSample>| samples.into_iter()
+      .map(|sample| ((sample.x, sample.y), (sample.weights, sample.depth)))
+      .collect::<HashMap<_, _>>();
+
+    let clockwise = by_pixel(rasterize_triangle(&a, &b, &c, 20, 20));
+    let counter_clockwise = by_pixel(rasterize_triangle(&a, &c, &b, 20, 20));
+    assert!(!clockwise.is_empty());
+    assert_eq!(clockwise.len(), counter_clockwise.len());
+    for (pixel, &((w1, w2, w3), depth)) in &clockwise {
+      // Swapping b and c swaps their weights and nothing else.
+      let ((u1, u3, u2), other_depth) = counter_clockwise[pixel];
+      assert_eq!((w1, w2, w3), (u1, u2, u3), "weights at {:?}", pixel);
+      assert_eq!(depth, other_depth, "depth at {:?}", pixel);
+    }
+  }
+
+  fn screen_vertex(x: f32, y: f32, z: f32) -> Vertex {
+    Vertex::new(Vec3::new(x, y, z), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros())
+  }
+
+  #[test]
+  fn collinear_vertices_give_no_fragments_but_slivers_still_rasterize() {
+    let light = Vec3::new(0.0, 0.0, 10.0);
+    let collinear = triangle(&screen_vertex(1.0, 1.0, 0.5), &screen_vertex(10.0, 10.0, 0.5), &screen_vertex(20.0, 20.0, 0.5), &light, 32, 32);
+    assert!(collinear.is_empty());
+
+    // Half a pixel tall at its highest point, around 7.5 square pixels.
+    let sliver = triangle(&screen_vertex(0.0, 5.5, 0.5), &screen_vertex(30.0, 5.5, 0.5), &screen_vertex(15.0, 6.0, 0.5), &light, 32, 32);
+    assert!(!sliver.is_empty());
+    for fragment in &sliver {
+      assert_eq!(fragment.position.y, 5.0);
+      assert!(fragment.depth.is_finite() && fragment.normal.iter().all(|c| c.is_finite()));
+    }
+  }
+
+  #[test]
+  fn rasterize_triangle_covers_one_pixel_the_whole_screen_or_nothing() {
+    let single = rasterize_triangle(&Vec3::new(5.2, 5.2, 0.5), &Vec3::new(5.9, 5.3, 0.5), &Vec3::new(5.4, 5.9, 0.5), 32, 32);
+    assert_eq!(single.iter().map(|sample| (sample.x, sample.y)).collect::<Vec<_>>(), [(5, 5)]);
+    assert_eq!(single[0].depth, 0.5);
+
+    let full = rasterize_triangle(&Vec3::new(-10.0, -10.0, 0.5), &Vec3::new(100.0, -10.0, 0.5), &Vec3::new(-10.0, 100.0, 0.5), 32, 32);
+    let pixels: HashSet<_> = full.iter().map(|sample| (sample.x, sample.y)).collect();
+    assert_eq!(full.len(), 32 * 32);
+    assert_eq!(pixels.len(), 32 * 32);
+
+    let beyond = rasterize_triangle(&Vec3::new(40.0, 40.0, 0.5), &Vec3::new(50.0, 40.0, 0.5), &Vec3::new(40.0, 50.0, 0.5), 32, 32);
+    let above_left = rasterize_triangle(&Vec3::new(-20.0, -20.0, 0.5), &Vec3::new(-5.0, -20.0, 0.5), &Vec3::new(-20.0, -5.0, 0.5), 32, 32);
+    assert!(beyond.is_empty() && above_left.is_empty());
+  }
+
+  #[cfg(feature = "f64-interpolation")]
+  #[test]
+  fn f64_interpolation_misorders_fewer_nearly_coplanar_pixels() {
+    // The same plane of depth under two different long, thin triangles that
+    // reach far off both sides of the screen, the second pushed back by
+    // about two f32 steps. Every shared pixel should keep the first in front.
+    let plane = |x: f32, y: f32| 0.9 + 0.00004 * x + 0.001 * y;
+    let offset = 1.0e-7;
+    let on_plane = |x: f32, y: f32, shift: f32| Vec3::new(x, y, plane(x, y) + shift);
+    let front = [on_plane(-60000.0, -1.0, 0.0), on_plane(60000.0, 0.5, 0.0), on_plane(-60000.0, 19.0, 0.0)];
+    let back = [on_plane(62000.0, -3.0, offset), on_plane(62000.0, 19.5, offset), on_plane(-61000.0, 4.0, offset)];
+
+    // The same edge functions and depth sum evaluated in f32.
+    let rasterize_f32 = |[a, b, c]: [Vec3; 3]| {
+      let edge = |a: &Vec3, b: &Vec3, p: &Vec3| (p.x - a.x) * (b.y - a.y) - (p.y - a.y) * (b.x - a.x);
+      let area = edge(&a, &b, &c);
+      let mut depths = HashMap::new();
+      for y in 0..8 {
+        for x in 0..2048 {
+          let p = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+          let (w1, w2, w3) = (edge(&b, &c, &p) / area, edge(&c, &a, &p) / area, edge(&a, &b, &p) / area);
+          if [w1, w2, w3].iter().all(|w| (0.0..=1.0).contains(w)) {
+            depths.insert((x, y), a.z * w1 + b.z * w2 + c.z * w3);
+          }
+        }
+      }
+      depths
+    };
+    let rasterize_f64 = |[a, b, c]: [Vec3; 3]| rasterize_triangle(&a, &b, &c, 2048, 8)
+      .into_iter()
+      .map(|sample| ((sample.x, sample.y), sample.depth))
+      .collect::<HashMap<_, _>>();
+    let misordered = |front: HashMap<(usize, usize), f32>, back: HashMap<(usize, usize), f32>| {
+      front.iter().filter(|(pixel, depth)| back.get(pixel).is_some_and(|back| back <= depth)).count()
+    };
+
+    let with_f32 = misordered(rasterize_f32(front), rasterize_f32(back));
+    let with_f64 = misordered(rasterize_f64(front), rasterize_f64(back));
+    assert!(with_f64 < with_f32, "f64 misordered {} pixels, f32 {}", with_f64, with_f32);
+  }
+
+  #[test]
+  fn roughness_is_halfway_at_the_middle_of_an_edge() {
+    let light = Vec3::new(0.0, 0.0, 10.0);
+    let mut smooth = screen_vertex(2.0, 2.0, 0.5);
+    smooth.roughness = 0.0;
+    let (rough_right, rough_down) = (screen_vertex(30.0, 2.0, 0.5), screen_vertex(2.0, 30.0, 0.5));
+    assert_eq!((rough_right.roughness, rough_down.roughness), (1.0, 1.0));
+
+    let fragments = triangle(&smooth, &rough_right, &rough_down, &light, 32, 32);
+    let at = |x: f32, y: f32| fragments.iter().find(|f| f.position == Vec2::new(x, y)).expect("pixel covered").roughness;
+    // Pixel (16, 2) is centered half a pixel off the top edge's midpoint.
+    assert!((at(16.0, 2.0) - 0.5).abs() < 0.05, "{}", at(16.0, 2.0));
+    assert!((at(2.0, 16.0) - 0.5).abs() < 0.05, "{}", at(2.0, 16.0));
+    assert!(at(2.0, 2.0) < 0.1);
+  }
+}
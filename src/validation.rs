@@ -0,0 +1,79 @@
+use nalgebra_glm::{Mat3, Mat4, Vec3};
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+// Checks the inputs of a render() call for values that would otherwise show
+// up as a garbled frame: non-finite matrices or positions, and normals that
+// can't be normalized. Returns a message naming the first problem found.
+pub fn validate(uniforms: &Uniforms, vertices: &[Vertex]) -> Result<(), String> {
+    let matrices = [
+        ("model matrix", &uniforms.model_matrix),
+        ("previous model matrix", &uniforms.previous_model_matrix),
+        ("previous view-projection matrix", &uniforms.previous_view_projection),
+        ("view matrix", &uniforms.view_matrix),
+        ("projection matrix", &uniforms.projection_matrix),
+        ("viewport matrix", &uniforms.viewport_matrix),
+    ];
+    for (name, matrix) in matrices {
+        check_mat4(name, matrix)?;
+    }
+    check_mat3("normal matrix", &uniforms.normal_matrix)?;
+    check_vec3("sun position", &uniforms.sun_position)?;
+    check_vec3("camera position", &uniforms.camera_position)?;
+
+    for (i, vertex) in vertices.iter().enumerate() {
+        if !vertex.position.iter().all(|c| c.is_finite()) {
+            return Err(format!("vertex {} has a non-finite position {:?}", i, vertex.position.as_slice()));
+        }
+        let length = vertex.normal.magnitude();
+        if !length.is_finite() || length < f32::EPSILON {
+            return Err(format!("vertex {} has an unusable normal {:?}", i, vertex.normal.as_slice()));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_mat4(name: &str, matrix: &Mat4) -> Result<(), String> {
+    match matrix.iter().position(|c| !c.is_finite()) {
+        Some(i) => Err(format!("{} has a non-finite entry at row {}, column {}", name, i % 4, i / 4)),
+        None => Ok(()),
+    }
+}
+
+fn check_mat3(name: &str, matrix: &Mat3) -> Result<(), String> {
+    match matrix.iter().position(|c| !c.is_finite()) {
+        Some(i) => Err(format!("{} has a non-finite entry at row {}, column {}", name, i % 3, i / 3)),
+        None => Ok(()),
+    }
+}
+
+fn check_vec3(name: &str, vector: &Vec3) -> Result<(), String> {
+    if vector.iter().all(|c| c.is_finite()) {
+        Ok(())
+    } else {
+        Err(format!("{} is not finite: {:?}", name, vector.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_noise;
+    use crate::texture::Texture;
+    use nalgebra_glm::Vec2;
+
+    #[test]
+    fn nan_model_matrix_is_reported_and_clean_input_passes() {
+        let (noise, environment) = (create_noise(), Texture::new(1, 1, vec![0]));
+        let uniforms = crate::test_uniforms(&noise, &environment, 64, 64);
+        let vertices = [Vertex::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros())];
+        assert_eq!(validate(&uniforms, &vertices), Ok(()));
+
+        let mut model_matrix = Mat4::identity();
+        model_matrix[(1, 2)] = f32::NAN;
+        let broken = Uniforms { model_matrix, ..uniforms };
+        let err = validate(&broken, &vertices).unwrap_err();
+        assert!(err.starts_with("model matrix"), "{}", err);
+    }
+}
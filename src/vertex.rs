@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -7,8 +7,25 @@ pub struct Vertex {
   pub normal: Vec3,
   pub tex_coords: Vec2,
   pub color: Color,
+  pub ao: f32,
+  // 0.0 is mirror-like, 1.0 fully matte.
+  pub roughness: f32,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub world_position: Vec3,
+  pub clip_position: Vec4,
+  // Reciprocal of clip-space w, kept after the perspective divide for
+  // perspective-correct interpolation.
+  pub w_inv: f32,
+  pub velocity: Vec2,
+  pub shaded_color: Color,
+  pub shaded_emission: Color,
+  pub shaded_opacity: f32,
+  // Index into the model's MTL materials, if its mesh has one.
+  pub material_id: Option<usize>,
+  // Set by the vertex shader when clip-space w is too close to zero to
+  // divide by; the screen position is then meaningless.
+  pub is_clipped: bool,
 }
 
 impl Vertex {
@@ -18,12 +35,49 @@ impl Vertex {
       normal,
       tex_coords,
       color: Color::black(),
+      ao: 1.0,
+      roughness: 1.0,
       transformed_position: position,
       transformed_normal: normal,
+      world_position: position,
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
+      w_inv: 1.0,
+      velocity: Vec2::new(0.0, 0.0),
+      shaded_color: Color::black(),
+      shaded_emission: Color::black(),
+      shaded_opacity: 0.0,
+      material_id: None,
+      is_clipped: false,
     }
   }
 
-
+  // Linear blend of every attribute, used to build the new vertices that
+  // clipping introduces along triangle edges. The screen-space position is
+  // left for the caller to recompute from the blended clip position.
+  // Linear in clip space, so w_inv is recomputed from the blended w rather
+  // than blended itself.
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    let clip_position = self.clip_position.lerp(&other.clip_position, t);
+    Vertex {
+      position: self.position.lerp(&other.position, t),
+      normal: self.normal.lerp(&other.normal, t),
+      tex_coords: self.tex_coords.lerp(&other.tex_coords, t),
+      color: self.color.lerp(&other.color, t),
+      ao: self.ao + (other.ao - self.ao) * t,
+      roughness: self.roughness + (other.roughness - self.roughness) * t,
+      transformed_position: self.transformed_position.lerp(&other.transformed_position, t),
+      transformed_normal: self.transformed_normal.lerp(&other.transformed_normal, t),
+      world_position: self.world_position.lerp(&other.world_position, t),
+      clip_position,
+      w_inv: 1.0 / clip_position.w,
+      velocity: self.velocity.lerp(&other.velocity, t),
+      shaded_color: self.shaded_color.lerp(&other.shaded_color, t),
+      shaded_emission: self.shaded_emission.lerp(&other.shaded_emission, t),
+      shaded_opacity: self.shaded_opacity + (other.shaded_opacity - self.shaded_opacity) * t,
+      material_id: self.material_id,
+      is_clipped: clip_position.w.abs() < f32::EPSILON,
+    }
+  }
 }
 
 impl Default for Vertex {
@@ -33,8 +87,19 @@ impl Default for Vertex {
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color: Color::black(),
+      ao: 1.0,
+      roughness: 1.0,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      w_inv: 1.0,
+      velocity: Vec2::new(0.0, 0.0),
+      shaded_color: Color::black(),
+      shaded_emission: Color::black(),
+      shaded_opacity: 0.0,
+      material_id: None,
+      is_clipped: false,
     }
   }
 }